@@ -0,0 +1,297 @@
+use crate::{claims_from_str, Claim, ClaimId, Error, Result};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+// Side length of the square buckets that make up our spatial index. Each claim is registered in
+// every bucket that it touches, so a point query only needs to look at a single bucket, and two
+// claims can only overlap if they share at least one bucket.
+
+const BUCKET_SIZE: u32 = 64;
+
+type BucketId = (u32, u32);
+type Area = u64;
+
+#[derive(Debug, PartialEq)]
+pub struct Overlap {
+    pub a: ClaimId,
+    pub b: ClaimId,
+    pub area: Area,
+}
+
+// A set of claims, indexed by the buckets of fabric that they cover.
+
+pub struct Fabric {
+    claims: Vec<Claim>,
+    ids: HashMap<ClaimId, usize>,
+    buckets: HashMap<BucketId, Vec<usize>>,
+}
+
+impl Fabric {
+    pub fn new(claims: Vec<Claim>) -> Result<Self> {
+        let mut ids = HashMap::new();
+        let mut buckets: HashMap<BucketId, Vec<usize>> = HashMap::new();
+        for (i, claim) in claims.iter().enumerate() {
+            if ids.insert(claim.id, i).is_some() {
+                return Err(Error::from(format!("duplicate claim id: #{}", claim.id)));
+            }
+            // Empty claims don't cover any points, so they never need to be looked up.
+            if claim.dx == 0 || claim.dy == 0 {
+                continue;
+            }
+            for bx in claim.x / BUCKET_SIZE..=(claim.x_max() - 1) / BUCKET_SIZE {
+                for by in claim.y / BUCKET_SIZE..=(claim.y_max() - 1) / BUCKET_SIZE {
+                    buckets.entry((bx, by)).or_default().push(i);
+                }
+            }
+        }
+        Ok(Fabric {
+            claims,
+            ids,
+            buckets,
+        })
+    }
+
+    // Returns the ids of every claim covering the square inch at (x, y), in ascending order.
+
+    pub fn claims_at(&self, x: u32, y: u32) -> Vec<ClaimId> {
+        let mut ids: Vec<ClaimId> = self
+            .buckets
+            .get(&(x / BUCKET_SIZE, y / BUCKET_SIZE))
+            .map(|bucket| {
+                bucket
+                    .iter()
+                    .map(|&i| &self.claims[i])
+                    .filter(|claim| claim.contains(x, y))
+                    .map(|claim| claim.id)
+                    .collect()
+            })
+            .unwrap_or_default();
+        ids.sort_unstable();
+        ids
+    }
+
+    // Returns the number of square inches covered by at least one of the given claims.
+
+    pub fn area(&self, ids: &[ClaimId]) -> Result<Area> {
+        let claims = ids
+            .iter()
+            .map(|id| self.get(*id))
+            .collect::<Result<Vec<&Claim>>>()?;
+        Ok(DepthMap::new(&claims)
+            .iter_cells()
+            .filter(|cell| cell.depth > 0)
+            .map(|cell| cell.area)
+            .sum())
+    }
+
+    // Returns every pair of claims that share at least one square inch, along with the size of
+    // the shared area. Pairs are ordered by their ids, with `a < b` in each pair.
+
+    pub fn overlapping_pairs(&self) -> Vec<Overlap> {
+        let mut seen = HashSet::new();
+        let mut overlaps = vec![];
+        for bucket in self.buckets.values() {
+            for (n, &i) in bucket.iter().enumerate() {
+                for &j in &bucket[n + 1..] {
+                    let (a, b) = (&self.claims[i], &self.claims[j]);
+                    let pair = (a.id.min(b.id), a.id.max(b.id));
+                    if !seen.insert(pair) {
+                        continue;
+                    }
+                    let area = a.intersection_area(b);
+                    if area > 0 {
+                        overlaps.push(Overlap {
+                            a: pair.0,
+                            b: pair.1,
+                            area,
+                        });
+                    }
+                }
+            }
+        }
+        overlaps.sort_unstable_by_key(|overlap| (overlap.a, overlap.b));
+        overlaps
+    }
+
+    // Returns the largest number of claims that cover any single square inch.
+
+    pub fn max_depth(&self) -> u32 {
+        self.deepest_point().map_or(0, |(depth, _)| depth)
+    }
+
+    // Returns the (x, y) of a square inch covered by the most claims, along with its depth.
+
+    pub fn deepest_point(&self) -> Option<(u32, (u32, u32))> {
+        let claims: Vec<&Claim> = self.claims.iter().collect();
+        DepthMap::new(&claims)
+            .iter_cells()
+            .filter(|cell| cell.depth > 0)
+            .max_by_key(|cell| (cell.depth, Reverse((cell.y, cell.x))))
+            .map(|cell| (cell.depth, (cell.x, cell.y)))
+    }
+
+    fn get(&self, id: ClaimId) -> Result<&Claim> {
+        self.ids
+            .get(&id)
+            .map(|&i| &self.claims[i])
+            .ok_or_else(|| Error::from(format!("unknown claim id: #{}", id)))
+    }
+}
+
+impl FromStr for Fabric {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Fabric::new(claims_from_str(s)?)
+    }
+}
+
+// Counts how many claims cover each cell of a coordinate-compressed grid. The cell boundaries are
+// the edges of the claims, so every square inch within a cell is covered by the same claims, and
+// we never have to walk the individual points of a claim.
+
+struct Cell {
+    x: u32,
+    y: u32,
+    area: Area,
+    depth: u32,
+}
+
+struct DepthMap {
+    xs: Vec<u32>,
+    ys: Vec<u32>,
+    depths: Vec<u32>,
+}
+
+impl DepthMap {
+    fn new(claims: &[&Claim]) -> Self {
+        let mut xs: Vec<u32> = claims.iter().flat_map(|c| vec![c.x, c.x_max()]).collect();
+        let mut ys: Vec<u32> = claims.iter().flat_map(|c| vec![c.y, c.y_max()]).collect();
+        xs.sort_unstable();
+        xs.dedup();
+        ys.sort_unstable();
+        ys.dedup();
+
+        // Build a 2d difference array, with one extra row and column for the far edges, then take
+        // its prefix sums to find the depth of each cell.
+        let width = xs.len();
+        let mut depths = vec![0i64; width * ys.len()];
+        let index = |coords: &[u32], value: u32| coords.binary_search(&value).unwrap();
+        for claim in claims {
+            let (x0, x1) = (index(&xs, claim.x), index(&xs, claim.x_max()));
+            let (y0, y1) = (index(&ys, claim.y), index(&ys, claim.y_max()));
+            depths[y0 * width + x0] += 1;
+            depths[y0 * width + x1] -= 1;
+            depths[y1 * width + x0] -= 1;
+            depths[y1 * width + x1] += 1;
+        }
+        for y in 0..ys.len() {
+            for x in 0..width {
+                let left = if x > 0 { depths[y * width + x - 1] } else { 0 };
+                let up = if y > 0 {
+                    depths[(y - 1) * width + x]
+                } else {
+                    0
+                };
+                let diagonal = if x > 0 && y > 0 {
+                    depths[(y - 1) * width + x - 1]
+                } else {
+                    0
+                };
+                depths[y * width + x] += left + up - diagonal;
+            }
+        }
+
+        DepthMap {
+            xs,
+            ys,
+            depths: depths.into_iter().map(|depth| depth as u32).collect(),
+        }
+    }
+
+    // Iterates over each cell in the grid.
+
+    fn iter_cells(&self) -> impl Iterator<Item = Cell> + '_ {
+        let width = self.xs.len();
+        self.ys.windows(2).enumerate().flat_map(move |(y, ys)| {
+            self.xs.windows(2).enumerate().map(move |(x, xs)| Cell {
+                x: xs[0],
+                y: ys[0],
+                area: Area::from(xs[1] - xs[0]) * Area::from(ys[1] - ys[0]),
+                depth: self.depths[y * width + x],
+            })
+        })
+    }
+}
+
+#[test]
+fn test_claims_at() -> Result<()> {
+    let fabric = "#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2\n".parse::<Fabric>()?;
+    assert_eq!(fabric.claims_at(0, 0), vec![]);
+    assert_eq!(fabric.claims_at(1, 3), vec![1]);
+    assert_eq!(fabric.claims_at(3, 3), vec![1, 2]);
+    assert_eq!(fabric.claims_at(4, 4), vec![1, 2]);
+    assert_eq!(fabric.claims_at(5, 5), vec![3]);
+    assert_eq!(fabric.claims_at(7, 7), vec![]);
+    Ok(())
+}
+
+#[test]
+fn test_fabric_queries() -> Result<()> {
+    let fabric =
+        "#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2\n#4 @ 4,4: 2x2\n".parse::<Fabric>()?;
+    assert_eq!(fabric.area(&[1])?, 16);
+    assert_eq!(fabric.area(&[1, 2])?, 28);
+    assert_eq!(fabric.area(&[1, 2, 3])?, 32);
+    assert_eq!(fabric.area(&[1, 2, 3, 4])?, 32);
+    assert!(fabric.area(&[5]).is_err());
+
+    assert_eq!(
+        fabric.overlapping_pairs(),
+        vec![
+            Overlap {
+                a: 1,
+                b: 2,
+                area: 4
+            },
+            Overlap {
+                a: 1,
+                b: 4,
+                area: 2
+            },
+            Overlap {
+                a: 2,
+                b: 4,
+                area: 2
+            },
+            Overlap {
+                a: 3,
+                b: 4,
+                area: 1
+            },
+        ]
+    );
+    assert_eq!(fabric.max_depth(), 3);
+    assert_eq!(fabric.deepest_point(), Some((3, (4, 4))));
+    assert_eq!(fabric.claims_at(4, 4), vec![1, 2, 4]);
+    Ok(())
+}
+
+#[test]
+fn test_fabric_spans_buckets() -> Result<()> {
+    let fabric = "#1 @ 60,60: 10x10\n#2 @ 68,0: 100x61\n".parse::<Fabric>()?;
+    assert_eq!(fabric.claims_at(69, 60), vec![1, 2]);
+    assert_eq!(fabric.claims_at(69, 61), vec![1]);
+    assert_eq!(
+        fabric.overlapping_pairs(),
+        vec![Overlap {
+            a: 1,
+            b: 2,
+            area: 2
+        }]
+    );
+    assert_eq!(fabric.max_depth(), 2);
+    assert_eq!(fabric.area(&[1, 2])?, 100 + 6100 - 2);
+    Ok(())
+}
@@ -4,11 +4,17 @@ use regex::Regex;
 use std::io::{self, Read, Write};
 use std::str::FromStr;
 
+mod fabric;
+
+use fabric::Fabric;
+
 type Error = Box<dyn std::error::Error>;
 type Result<T> = std::result::Result<T, Error>;
 
 const GRID_SIZE: usize = 1000;
 
+type ClaimId = u32;
+
 fn main() -> Result<()> {
     let mut input = String::new();
     io::stdin().lock().read_to_string(&mut input)?;
@@ -16,7 +22,7 @@ fn main() -> Result<()> {
     let claims = claims_from_str(&input)?;
 
     // TODO: Program hangs if we use u32. Why??
-    let mut grid = [[u8::from(0); GRID_SIZE]; GRID_SIZE];
+    let mut grid = [[0u8; GRID_SIZE]; GRID_SIZE];
 
     writeln!(
         io::stdout(),
@@ -29,6 +35,25 @@ fn main() -> Result<()> {
         "non-overlapping claim: {}",
         get_non_overlapping(&claims, &grid)?
     )?;
+
+    let fabric = input.parse::<Fabric>()?;
+    writeln!(io::stdout(), "max overlap depth: {}", fabric.max_depth())?;
+    if let Some((_, (x, y))) = fabric.deepest_point() {
+        writeln!(
+            io::stdout(),
+            "claims covering ({}, {}): {:?}",
+            x,
+            y,
+            fabric.claims_at(x, y)
+        )?;
+    }
+    writeln!(
+        io::stdout(),
+        "overlapping claim pairs: {}",
+        fabric.overlapping_pairs().len()
+    )?;
+    let ids: Vec<ClaimId> = claims.iter().map(|claim| claim.id).collect();
+    writeln!(io::stdout(), "total claimed area: {}", fabric.area(&ids)?)?;
     Ok(())
 }
 
@@ -40,29 +65,22 @@ fn claims_from_str(input: &str) -> Result<Vec<Claim>> {
     Ok(claims)
 }
 
-fn count_overlaps(claims: &Vec<Claim>, grid: &mut [[u8; 1000]; 1000]) -> Result<i32> {
+fn count_overlaps(claims: &[Claim], grid: &mut [[u8; 1000]; 1000]) -> Result<i32> {
     claims.iter().for_each(|c| {
         c.iter_points().for_each(|(x, y)| {
             grid[x][y] = grid[x][y].saturating_add(1);
         })
     });
 
-    // TODO: How to avoid the for loops here? Perhaps we would need to bring in
-    // https://crates.io/crates/ndarray ?
-
-    let mut counts = 0;
-    for i in 0..GRID_SIZE {
-        for j in 0..GRID_SIZE {
-            if grid[i][j] > 1 {
-                counts += 1;
-            }
-        }
-    }
+    let counts = grid
+        .iter()
+        .map(|row| row.iter().filter(|&&count| count > 1).count() as i32)
+        .sum();
 
     Ok(counts)
 }
 
-fn get_non_overlapping(claims: &Vec<Claim>, grid: &[[u8; 1000]; 1000]) -> Result<u32> {
+fn get_non_overlapping(claims: &[Claim], grid: &[[u8; 1000]; 1000]) -> Result<ClaimId> {
     Ok(claims
         .iter()
         .find(|claim| claim.iter_points().all(|(x, y)| grid[x][y] < 2))
@@ -71,7 +89,7 @@ fn get_non_overlapping(claims: &Vec<Claim>, grid: &[[u8; 1000]; 1000]) -> Result
 }
 
 struct Claim {
-    id: u32,
+    id: ClaimId,
     x: u32,
     y: u32,
     dx: u32,
@@ -79,13 +97,39 @@ struct Claim {
 }
 
 impl Claim {
-    fn iter_points(&self) -> IterPoints {
+    fn iter_points(&self) -> IterPoints<'_> {
         IterPoints {
-            claim: &self,
+            claim: self,
             px: self.x,
             py: self.y,
         }
     }
+
+    fn x_max(&self) -> u32 {
+        self.x + self.dx
+    }
+
+    fn y_max(&self) -> u32 {
+        self.y + self.dy
+    }
+
+    fn contains(&self, x: u32, y: u32) -> bool {
+        (self.x..self.x_max()).contains(&x) && (self.y..self.y_max()).contains(&y)
+    }
+
+    // Returns the number of square inches shared by both claims.
+
+    fn intersection_area(&self, other: &Claim) -> u64 {
+        let dx = self
+            .x_max()
+            .min(other.x_max())
+            .saturating_sub(self.x.max(other.x));
+        let dy = self
+            .y_max()
+            .min(other.y_max())
+            .saturating_sub(self.y.max(other.y));
+        u64::from(dx) * u64::from(dy)
+    }
 }
 
 struct IterPoints<'c> {
@@ -170,11 +214,11 @@ impl FromStr for Claim {
 fn test_overlaps() -> Result<()> {
     let s = "#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2\n";
 
-    let claims: Vec<Claim> = claims_from_str(&s)?;
+    let claims: Vec<Claim> = claims_from_str(s)?;
     assert_eq!(claims[0].id, 1);
 
     // TODO: Program hangs if we use u32. Why??
-    let mut grid = [[u8::from(0); GRID_SIZE]; GRID_SIZE];
+    let mut grid = [[0u8; GRID_SIZE]; GRID_SIZE];
 
     assert_eq!(count_overlaps(&claims, &mut grid)?, 4);
 
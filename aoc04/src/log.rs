@@ -0,0 +1,392 @@
+use crate::GuardId;
+use regex::Regex;
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use std::str::FromStr;
+
+// Minutes within the midnight hour. Only sleep during this hour counts towards a guard's schedule.
+
+pub type Minute = u8;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord)]
+pub struct DateTime {
+    pub year: u32,
+    pub month: u8,
+    pub day: u16,
+    pub hour: u8,
+    pub minute: u8,
+}
+
+impl DateTime {
+    // Returns midnight of the night that a shift starting at this time covers. Shifts that begin
+    // before midnight (eg: at 23:58) belong to the following day.
+
+    fn night(&self) -> DateTime {
+        let midnight = DateTime {
+            hour: 0,
+            minute: 0,
+            ..*self
+        };
+        if self.hour < 12 {
+            midnight
+        } else {
+            midnight.next_day()
+        }
+    }
+
+    fn next_day(&self) -> DateTime {
+        let is_leap = self.year.is_multiple_of(4)
+            && (!self.year.is_multiple_of(100) || self.year.is_multiple_of(400));
+        let days_in_month = match self.month {
+            2 if is_leap => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        };
+        let (year, month, day) = if self.day < days_in_month {
+            (self.year, self.month, self.day + 1)
+        } else if self.month < 12 {
+            (self.year, self.month + 1, 1)
+        } else {
+            (self.year + 1, 1, 1)
+        };
+        DateTime {
+            year,
+            month,
+            day,
+            ..*self
+        }
+    }
+
+    fn same_day(&self, other: &DateTime) -> bool {
+        (self.year, self.month, self.day) == (other.year, other.month, other.day)
+    }
+}
+
+impl Display for DateTime {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute
+        )
+    }
+}
+
+// Describes why a guard log couldn't be turned into shifts, along with the timestamp of the event
+// at fault.
+
+#[derive(Debug, PartialEq)]
+pub enum LogError {
+    InvalidLine(String),
+    UnknownStatement {
+        timestamp: DateTime,
+        statement: String,
+    },
+    NoGuardOnDuty {
+        timestamp: DateTime,
+    },
+    AlreadyAsleep {
+        timestamp: DateTime,
+    },
+    AlreadyAwake {
+        timestamp: DateTime,
+    },
+    ShiftEndedAsleep {
+        timestamp: DateTime,
+    },
+}
+
+impl Display for LogError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use LogError::*;
+        match self {
+            InvalidLine(line) => write!(f, "unable to parse log line: {:?}", line),
+            UnknownStatement {
+                timestamp,
+                statement,
+            } => write!(f, "[{}] unknown statement: {:?}", timestamp, statement),
+            NoGuardOnDuty { timestamp } => {
+                write!(
+                    f,
+                    "[{}] event happened before any guard began a shift",
+                    timestamp
+                )
+            }
+            AlreadyAsleep { timestamp } => {
+                write!(f, "[{}] guard fell asleep while already asleep", timestamp)
+            }
+            AlreadyAwake { timestamp } => {
+                write!(f, "[{}] guard woke up while already awake", timestamp)
+            }
+            ShiftEndedAsleep { timestamp } => write!(
+                f,
+                "[{}] guard fell asleep, but never woke up before the shift ended",
+                timestamp
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LogError {}
+
+#[derive(Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub enum EventKind {
+    GuardStart { guard_id: GuardId },
+    Asleep,
+    Wakeup,
+}
+
+// NOTE: the timestamp comes first, so that deriving Ord sorts events chronologically.
+
+#[derive(Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub struct Event {
+    pub timestamp: DateTime,
+    pub kind: EventKind,
+}
+
+impl FromStr for Event {
+    type Err = LogError;
+
+    fn from_str(s: &str) -> Result<Self, LogError> {
+        lazy_static! {
+            // NOTE: the (?x) prefix allows us to escape white spaces
+            static ref RE: Regex = Regex::new(
+                r"(?x)
+                    ^\[
+                    # year, month, day, time
+                    (?P<year>[0-9]{4})-(?P<month>[0-9]{2})-(?P<day>[0-9]{2})
+                    \s+
+                    # hour, minute
+                    (?P<hour>[0-9]{2}):(?P<minute>[0-9]{2})
+                    \]\s+
+                    # Event type, and guard number, if available:
+                    (?:Guard\ \#(?P<id>[0-9]+)\ begins\ shift|(?P<sleep>.*))
+                    $"
+            )
+            .unwrap();
+        }
+
+        let invalid = || LogError::InvalidLine(s.to_string());
+        let caps = RE.captures(s.trim()).ok_or_else(invalid)?;
+
+        let timestamp = DateTime {
+            year: caps["year"].parse().map_err(|_| invalid())?,
+            month: caps["month"].parse().map_err(|_| invalid())?,
+            // equivalent way of getting the group:
+            day: caps
+                .name("day")
+                .unwrap()
+                .as_str()
+                .parse()
+                .map_err(|_| invalid())?,
+            hour: caps["hour"].parse().map_err(|_| invalid())?,
+            minute: caps["minute"].parse().map_err(|_| invalid())?,
+        };
+
+        use EventKind::*;
+
+        let kind = if let Some(guard_id) = caps.name("id") {
+            GuardStart {
+                guard_id: guard_id.as_str().parse().map_err(|_| invalid())?,
+            }
+        } else {
+            match caps.name("sleep").map(|sleep| sleep.as_str()) {
+                Some("falls asleep") => Asleep,
+                Some("wakes up") => Wakeup,
+                statement => {
+                    return Err(LogError::UnknownStatement {
+                        timestamp,
+                        statement: statement.unwrap_or_default().to_string(),
+                    })
+                }
+            }
+        };
+
+        Ok(Event { timestamp, kind })
+    }
+}
+
+// A single guard's shift, and the minutes of the midnight hour that they spent asleep.
+
+#[derive(Debug, PartialEq)]
+pub struct Shift {
+    pub guard_id: GuardId,
+    pub start: DateTime,
+    pub naps: Vec<Range<Minute>>,
+}
+
+impl Shift {
+    fn new(guard_id: GuardId, start: DateTime) -> Self {
+        Shift {
+            guard_id,
+            start,
+            naps: vec![],
+        }
+    }
+
+    // Returns midnight of the night covered by this shift.
+
+    pub fn night(&self) -> DateTime {
+        self.start.night()
+    }
+
+    // Clamps a timestamp onto the midnight hour of this shift, so that sleeping before midnight
+    // counts from minute 0, and sleeping past 00:59 counts until minute 60.
+
+    fn minute_of(&self, timestamp: &DateTime) -> Minute {
+        let night = self.night();
+        if timestamp < &night {
+            0
+        } else if timestamp.same_day(&night) && timestamp.hour == 0 {
+            timestamp.minute
+        } else {
+            60
+        }
+    }
+}
+
+// Parses an unsorted guard log into shifts, in chronological order.
+//
+// Events that are logged more than once are only counted once. Guards who never fall asleep still
+// get a shift, with no naps.
+
+pub fn parse_shifts(input: &str) -> Result<Vec<Shift>, LogError> {
+    let mut events = input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.parse())
+        .collect::<Result<Vec<Event>, LogError>>()?;
+    events.sort();
+    events.dedup();
+
+    let mut shifts: Vec<Shift> = vec![];
+    let mut fell_asleep: Option<DateTime> = None;
+    for event in events {
+        let timestamp = event.timestamp;
+        if let EventKind::GuardStart { guard_id } = event.kind {
+            if let Some(timestamp) = fell_asleep {
+                return Err(LogError::ShiftEndedAsleep { timestamp });
+            }
+            shifts.push(Shift::new(guard_id, timestamp));
+            continue;
+        }
+
+        let shift = shifts
+            .last_mut()
+            .ok_or(LogError::NoGuardOnDuty { timestamp })?;
+        match event.kind {
+            EventKind::Asleep => {
+                if fell_asleep.is_some() {
+                    return Err(LogError::AlreadyAsleep { timestamp });
+                }
+                fell_asleep = Some(timestamp);
+            }
+            EventKind::Wakeup => {
+                let asleep = fell_asleep
+                    .take()
+                    .ok_or(LogError::AlreadyAwake { timestamp })?;
+                let nap = shift.minute_of(&asleep)..shift.minute_of(&timestamp);
+                if !nap.is_empty() {
+                    shift.naps.push(nap);
+                }
+            }
+            EventKind::GuardStart { .. } => unreachable!(),
+        }
+    }
+    if let Some(timestamp) = fell_asleep {
+        return Err(LogError::ShiftEndedAsleep { timestamp });
+    }
+    Ok(shifts)
+}
+
+#[test]
+fn test_shift_before_midnight() -> Result<(), LogError> {
+    let s = "\
+[1518-12-31 23:58] Guard #99 begins shift
+[1518-12-31 23:59] falls asleep
+[1519-01-01 00:03] wakes up
+[1519-01-01 00:40] falls asleep
+[1519-01-01 01:10] wakes up
+[1519-01-01 23:45] Guard #10 begins shift
+";
+    let shifts = parse_shifts(s)?;
+    assert_eq!(shifts.len(), 2);
+    assert_eq!(shifts[0].guard_id, 99);
+    assert_eq!(shifts[0].night().to_string(), "1519-01-01 00:00");
+    assert_eq!(shifts[0].naps, vec![0..3, 40..60]);
+    assert_eq!(shifts[1].guard_id, 10);
+    assert_eq!(shifts[1].night().to_string(), "1519-01-02 00:00");
+    assert_eq!(shifts[1].naps, vec![]);
+    Ok(())
+}
+
+#[test]
+fn test_duplicated_events() -> Result<(), LogError> {
+    let s = "\
+[1518-11-01 00:05] falls asleep
+[1518-11-01 00:00] Guard #10 begins shift
+[1518-11-01 00:05] falls asleep
+[1518-11-01 00:25] wakes up
+[1518-11-01 00:00] Guard #10 begins shift
+";
+    let shifts = parse_shifts(s)?;
+    assert_eq!(shifts.len(), 1);
+    assert_eq!(shifts[0].naps, vec![5..25]);
+    Ok(())
+}
+
+#[test]
+fn test_malformed_logs() {
+    let timestamp = DateTime {
+        year: 1518,
+        month: 11,
+        day: 1,
+        hour: 0,
+        minute: 5,
+    };
+    let parse = |lines: &[&str]| parse_shifts(&lines.join("\n"));
+    let start = "[1518-11-01 00:00] Guard #10 begins shift";
+
+    assert_eq!(
+        parse(&[start, "[1518-11-01 00:05] starts snoring"]),
+        Err(LogError::UnknownStatement {
+            timestamp,
+            statement: "starts snoring".to_string(),
+        })
+    );
+    assert_eq!(
+        parse(&["[1518-11-01 00:05] falls asleep"]),
+        Err(LogError::NoGuardOnDuty { timestamp })
+    );
+    assert_eq!(
+        parse(&[start, "[1518-11-01 00:05] wakes up"]),
+        Err(LogError::AlreadyAwake { timestamp })
+    );
+    assert_eq!(
+        parse(&[
+            start,
+            "[1518-11-01 00:01] falls asleep",
+            "[1518-11-01 00:05] falls asleep"
+        ]),
+        Err(LogError::AlreadyAsleep { timestamp })
+    );
+    assert_eq!(
+        parse(&[
+            start,
+            "[1518-11-01 00:05] falls asleep",
+            "[1518-11-01 23:58] Guard #99 begins shift"
+        ]),
+        Err(LogError::ShiftEndedAsleep { timestamp })
+    );
+    assert_eq!(
+        parse(&[start, "[1518-11-01 00:05] falls asleep"]),
+        Err(LogError::ShiftEndedAsleep { timestamp })
+    );
+    assert_eq!(
+        parse(&["[1518-11-01] falls asleep"]),
+        Err(LogError::InvalidLine(
+            "[1518-11-01] falls asleep".to_string()
+        ))
+    );
+}
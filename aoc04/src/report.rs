@@ -0,0 +1,305 @@
+use crate::log::{parse_shifts, DateTime, Minute, Shift};
+use crate::{Error, GuardId, Result, SleepSchedule};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::str::FromStr;
+
+// The naps that a guard took during a single night.
+
+pub struct Night {
+    pub date: DateTime,
+    pub naps: Vec<Range<Minute>>,
+}
+
+pub struct GuardReport {
+    pub id: GuardId,
+    pub nights: Vec<Night>,
+    // How many nights the guard was asleep during each minute of the midnight hour.
+    pub heatmap: SleepSchedule,
+}
+
+impl GuardReport {
+    fn new(id: GuardId) -> Self {
+        GuardReport {
+            id,
+            nights: vec![],
+            heatmap: [0; 60],
+        }
+    }
+
+    pub fn total_minutes(&self) -> u32 {
+        self.heatmap.iter().sum()
+    }
+
+    // Returns the highest number of nights that the guard spent asleep during the same minute.
+
+    pub fn max_frequency(&self) -> u32 {
+        *self.heatmap.iter().max().unwrap()
+    }
+
+    // Returns every minute that the guard was most frequently asleep during, in ascending order.
+    // More than one minute means that there is a tie. Guards who never sleep have no such minutes.
+
+    pub fn sleepiest_minutes(&self) -> Vec<Minute> {
+        let max = self.max_frequency();
+        if max == 0 {
+            return vec![];
+        }
+        (0..60)
+            .filter(|&min| self.heatmap[usize::from(min)] == max)
+            .collect()
+    }
+}
+
+// Per-guard sleep analytics, ordered by guard id.
+
+pub struct SleepReport {
+    guards: Vec<GuardReport>,
+}
+
+impl SleepReport {
+    pub fn new(shifts: Vec<Shift>) -> Self {
+        let mut guards: BTreeMap<GuardId, GuardReport> = BTreeMap::new();
+        for shift in shifts {
+            let guard = guards
+                .entry(shift.guard_id)
+                .or_insert_with(|| GuardReport::new(shift.guard_id));
+            for nap in &shift.naps {
+                for min in nap.clone() {
+                    guard.heatmap[usize::from(min)] += 1;
+                }
+            }
+            guard.nights.push(Night {
+                date: shift.night(),
+                naps: shift.naps,
+            });
+        }
+        SleepReport {
+            guards: guards.into_values().collect(),
+        }
+    }
+
+    pub fn guards(&self) -> &[GuardReport] {
+        &self.guards
+    }
+
+    // Returns the ids of every guard tied for the most total minutes asleep. If nobody ever
+    // sleeps, there is no sleepiest guard.
+
+    pub fn sleepiest_guards(&self) -> Vec<GuardId> {
+        let max = match self.guards.iter().map(|guard| guard.total_minutes()).max() {
+            None | Some(0) => return vec![],
+            Some(max) => max,
+        };
+        self.guards
+            .iter()
+            .filter(|guard| guard.total_minutes() == max)
+            .map(|guard| guard.id)
+            .collect()
+    }
+
+    // Returns every (guard, minute) tied for the most nights spent asleep on the same minute.
+
+    pub fn most_frequent_minutes(&self) -> Vec<(GuardId, Minute)> {
+        let max = match self.guards.iter().map(|guard| guard.max_frequency()).max() {
+            None | Some(0) => return vec![],
+            Some(max) => max,
+        };
+        self.guards
+            .iter()
+            .filter(|guard| guard.max_frequency() == max)
+            .flat_map(|guard| {
+                guard
+                    .sleepiest_minutes()
+                    .into_iter()
+                    .map(move |min| (guard.id, min))
+            })
+            .collect()
+    }
+
+    // One row per guard, with tied sleepiest minutes separated by a ';'.
+
+    pub fn write_summary_csv<W: Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(
+            w,
+            "guard,total_minutes,nights,max_frequency,sleepiest_minutes"
+        )?;
+        for guard in &self.guards {
+            let minutes: Vec<String> = guard
+                .sleepiest_minutes()
+                .iter()
+                .map(|min| min.to_string())
+                .collect();
+            writeln!(
+                w,
+                "{},{},{},{},{}",
+                guard.id,
+                guard.total_minutes(),
+                guard.nights.len(),
+                guard.max_frequency(),
+                minutes.join(";")
+            )?;
+        }
+        Ok(())
+    }
+
+    // One row per guard, and one column per minute of the midnight hour.
+
+    pub fn write_heatmap_csv<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let minutes: Vec<String> = (0..60).map(|min: u8| min.to_string()).collect();
+        writeln!(w, "guard,{}", minutes.join(","))?;
+        for guard in &self.guards {
+            let counts: Vec<String> = guard.heatmap.iter().map(|n| n.to_string()).collect();
+            writeln!(w, "{},{}", guard.id, counts.join(","))?;
+        }
+        Ok(())
+    }
+
+    // One row per nap, where `wakes` is the first minute that the guard is awake again.
+
+    pub fn write_naps_csv<W: Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "guard,night,asleep,wakes")?;
+        for guard in &self.guards {
+            for night in &guard.nights {
+                for nap in &night.naps {
+                    writeln!(
+                        w,
+                        "{},{:04}-{:02}-{:02},{},{}",
+                        guard.id,
+                        night.date.year,
+                        night.date.month,
+                        night.date.day,
+                        nap.start,
+                        nap.end
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Writes summary.csv, heatmap.csv and naps.csv into the given directory.
+
+    pub fn export_csv(&self, dir: &Path) -> Result<()> {
+        self.write_summary_csv(BufWriter::new(File::create(dir.join("summary.csv"))?))?;
+        self.write_heatmap_csv(BufWriter::new(File::create(dir.join("heatmap.csv"))?))?;
+        self.write_naps_csv(BufWriter::new(File::create(dir.join("naps.csv"))?))?;
+        Ok(())
+    }
+}
+
+impl FromStr for SleepReport {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(SleepReport::new(parse_shifts(s)?))
+    }
+}
+
+#[test]
+fn test_report() -> Result<()> {
+    let s = "\
+[1518-11-01 00:00] Guard #10 begins shift
+[1518-11-01 00:05] falls asleep
+[1518-11-01 00:25] wakes up
+[1518-11-01 00:30] falls asleep
+[1518-11-01 00:55] wakes up
+[1518-11-01 23:58] Guard #99 begins shift
+[1518-11-02 00:40] falls asleep
+[1518-11-02 00:50] wakes up
+[1518-11-03 00:05] Guard #10 begins shift
+[1518-11-03 00:24] falls asleep
+[1518-11-03 00:29] wakes up
+[1518-11-04 00:02] Guard #99 begins shift
+[1518-11-04 00:36] falls asleep
+[1518-11-04 00:46] wakes up
+[1518-11-05 00:03] Guard #99 begins shift
+[1518-11-05 00:45] falls asleep
+[1518-11-05 00:55] wakes up
+[1518-11-06 00:01] Guard #7 begins shift
+";
+    let report = s.parse::<SleepReport>()?;
+    let ids: Vec<GuardId> = report.guards().iter().map(|guard| guard.id).collect();
+    assert_eq!(ids, vec![7, 10, 99]);
+
+    let guard = &report.guards()[1];
+    assert_eq!(guard.total_minutes(), 50);
+    assert_eq!(guard.nights.len(), 2);
+    assert_eq!(guard.nights[1].naps, vec![24..29]);
+    assert_eq!(guard.sleepiest_minutes(), vec![24]);
+
+    let never_sleeps = &report.guards()[0];
+    assert_eq!(never_sleeps.total_minutes(), 0);
+    assert_eq!(never_sleeps.sleepiest_minutes(), vec![]);
+
+    assert_eq!(report.sleepiest_guards(), vec![10]);
+    assert_eq!(report.most_frequent_minutes(), vec![(99, 45)]);
+
+    let mut naps = vec![];
+    report.write_naps_csv(&mut naps)?;
+    assert_eq!(
+        String::from_utf8(naps)?.lines().take(3).collect::<Vec<_>>(),
+        vec![
+            "guard,night,asleep,wakes",
+            "10,1518-11-01,5,25",
+            "10,1518-11-01,30,55"
+        ]
+    );
+
+    let mut summary = vec![];
+    report.write_summary_csv(&mut summary)?;
+    assert_eq!(
+        String::from_utf8(summary)?,
+        "guard,total_minutes,nights,max_frequency,sleepiest_minutes\n\
+         7,0,1,0,\n\
+         10,50,2,2,24\n\
+         99,30,3,3,45\n"
+    );
+
+    let mut heatmap = vec![];
+    report.write_heatmap_csv(&mut heatmap)?;
+    let heatmap = String::from_utf8(heatmap)?;
+    let rows: Vec<&str> = heatmap.lines().collect();
+    assert_eq!(rows.len(), 4);
+    assert!(rows[0].starts_with("guard,0,1,2,"));
+    assert!(rows[0].ends_with(",58,59"));
+    assert!(rows[3].starts_with("99,0,0,"));
+    Ok(())
+}
+
+#[test]
+fn test_report_ties() -> Result<()> {
+    let s = "\
+[1518-11-01 00:00] Guard #10 begins shift
+[1518-11-01 00:05] falls asleep
+[1518-11-01 00:07] wakes up
+[1518-11-02 00:00] Guard #20 begins shift
+[1518-11-02 00:30] falls asleep
+[1518-11-02 00:31] wakes up
+[1518-11-02 00:40] falls asleep
+[1518-11-02 00:41] wakes up
+";
+    let report = s.parse::<SleepReport>()?;
+    assert_eq!(report.sleepiest_guards(), vec![10, 20]);
+    assert_eq!(
+        report.most_frequent_minutes(),
+        vec![(10, 5), (10, 6), (20, 30), (20, 40)]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_report_nobody_sleeps() -> Result<()> {
+    let s = "\
+[1518-11-01 00:00] Guard #10 begins shift
+[1518-11-02 00:00] Guard #20 begins shift
+";
+    let report = s.parse::<SleepReport>()?;
+    assert_eq!(report.guards().len(), 2);
+    assert_eq!(report.sleepiest_guards(), vec![]);
+    assert_eq!(report.most_frequent_minutes(), vec![]);
+    Ok(())
+}
@@ -1,94 +1,135 @@
+use rayon::prelude::*;
+use std::collections::BTreeSet;
 use std::io::{self, Read, Write};
 use std::str::FromStr;
 
+mod rules;
+
+use rules::{Reaction, ReactionRules, Unit};
+
 type Error = Box<dyn std::error::Error>;
 type Result<T, E = Error> = std::result::Result<T, E>;
 
 fn main() -> Result<()> {
     let mut input = String::new();
     std::io::stdin().read_to_string(&mut input)?;
-    let input = input.trim().parse::<AsciiEncodedString>()?; // removes a trailing LF escape char
+    let input = input.trim().parse::<Polymer>()?; // removes a trailing LF escape char
 
+    // The reaction rules can optionally be given as the first argument, eg: "case !x 🧪💥"
+    let rules = match std::env::args().nth(1) {
+        Some(rules) => rules.parse()?,
+        None => ReactionRules::default(),
+    };
+
+    let (reduced, reactions) = react_with_trace(&input, &rules);
     writeln!(
         io::stdout(),
         "length of polymer left: {}",
-        react(&input).len()
+        reduced.chars().count()
     )?;
+    writeln!(io::stdout(), "number of reactions: {}", reactions.len())?;
 
     writeln!(
         io::stdout(),
         "length of shortest inert polymer after 1 pair removal: {}",
-        find_shortest_inert_length(&input)
+        find_shortest_inert_length(&input, &rules)
     )?;
 
     Ok(())
 }
 
-struct AsciiEncodedString(pub String);
+struct Polymer(pub Vec<Unit>);
 
-impl FromStr for AsciiEncodedString {
+impl FromStr for Polymer {
     type Err = String;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if !s.is_ascii() {
-            return Err(String::from("input string is non-ascii!"));
+        if s.chars().any(char::is_whitespace) {
+            return Err(String::from("polymer contains whitespace!"));
         }
-        Ok(AsciiEncodedString(String::from(s)))
+        Ok(Polymer(s.chars().collect()))
     }
 }
 
 // Note that we can return a string slice from a function only if the returned slice is derived from
 // the lifetime of the originating string/slice
 
-fn react(polymer: &AsciiEncodedString) -> String {
-    let mut polymer = polymer.0.as_bytes().to_vec();
-    let mut i = 0;
-    loop {
-        if i + 1 >= polymer.len() {
-            return String::from_utf8(polymer).expect("should not have non-utf8 string");
-        }
-        if reacts(polymer[i], polymer[i + 1]) {
-            // remove the reacting polymers
-            polymer.remove(i + 1);
-            polymer.remove(i);
-            i = if i == 0 { 0 } else { i - 1 };
-        } else {
-            i += 1;
-        }
-    }
+fn react(polymer: &Polymer, rules: &ReactionRules) -> String {
+    reduce(polymer.0.iter().copied().enumerate(), rules, None)
 }
 
-// returns whether the two ascii values are the same code point, but with mismatched capitalization
+// Same as `react`, but also returns every reaction that happened, in the order that they happened.
 
-fn reacts(c1: u8, c2: u8) -> bool {
-    if c1 < c2 {
-        c2 - c1 == 32
-    } else {
-        c1 - c2 == 32
+fn react_with_trace(polymer: &Polymer, rules: &ReactionRules) -> (String, Vec<Reaction>) {
+    let mut trace = vec![];
+    let reduced = reduce(
+        polymer.0.iter().copied().enumerate(),
+        rules,
+        Some(&mut trace),
+    );
+    (reduced, trace)
+}
+
+// Reduces the polymer in a single pass, using a stack of the units that haven't reacted yet. Each
+// unit either reacts with the unit on top of the stack, or is pushed on top of it.
+//
+// Units are paired with their position in the original polymer, so that reactions can be traced.
+
+fn reduce(
+    units: impl Iterator<Item = (usize, Unit)>,
+    rules: &ReactionRules,
+    mut trace: Option<&mut Vec<Reaction>>,
+) -> String {
+    let mut stack: Vec<(usize, Unit)> = Vec::new();
+    for (position, unit) in units {
+        match stack.last() {
+            Some(&(top_position, top)) if rules.reacts(top, unit) => {
+                stack.pop();
+                if let Some(trace) = trace.as_mut() {
+                    trace.push(Reaction {
+                        positions: (top_position, position),
+                        units: (top, unit),
+                    });
+                }
+            }
+            _ => stack.push((position, unit)),
+        }
     }
+    stack.into_iter().map(|(_, unit)| unit).collect()
 }
 
 // find the shortest inert length after removing one polymer pair
-
-fn find_shortest_inert_length(polymer: &AsciiEncodedString) -> usize {
-    (b'A'..b'Z')
-        .map(|byte| {
-            let byte_pair = byte + 32;
-            let test_polymer = polymer
-                .0
-                .replace(char::from(byte), "")
-                .replace(char::from(byte_pair), "")
-                .parse()
-                .expect("test_polymer should remain ascii encoded");
-            react(&test_polymer).len()
+//
+// With the default rules, removing a unit type never prevents a reaction from happening, so we can
+// start from the already reduced polymer, instead of reducing the original polymer once per unit
+// type. Other rules don't guarantee that, so the original polymer is reduced each time.
+
+fn find_shortest_inert_length(polymer: &Polymer, rules: &ReactionRules) -> usize {
+    let units: Vec<Unit> = if rules.is_default() {
+        react(polymer, rules).chars().collect()
+    } else {
+        polymer.0.clone()
+    };
+    let unit_types: BTreeSet<Unit> = units.iter().map(|&unit| rules.unit_type(unit)).collect();
+    unit_types
+        .into_par_iter()
+        .map(|removed| {
+            let remaining = units
+                .iter()
+                .copied()
+                .filter(|&unit| rules.unit_type(unit) != removed);
+            reduce(remaining.enumerate(), rules, None).chars().count()
         })
         .min()
-        .expect("should not have an empty iter")
+        .unwrap_or(0)
 }
 
 #[test]
 fn test_shortest_inert_length() -> Result<()> {
     let polymer = "dabAcCaCBAcCcaDA".parse()?;
-    assert_eq!(find_shortest_inert_length(&polymer), 4);
+    assert_eq!(
+        find_shortest_inert_length(&polymer, &ReactionRules::default()),
+        4
+    );
     println!("shortest inert length successful!");
     Ok(())
 }
@@ -96,8 +137,8 @@ fn test_shortest_inert_length() -> Result<()> {
 #[test]
 fn test_react() -> Result<()> {
     let polymer = "dabAcCaCBAcCcaDA".parse()?;
-    assert_eq!(react(&polymer), "dabCBAcaDA");
-    assert_eq!(react(&polymer).len(), 10);
+    assert_eq!(react(&polymer, &ReactionRules::default()), "dabCBAcaDA");
+    assert_eq!(react(&polymer, &ReactionRules::default()).len(), 10);
     println!("react successful!");
     Ok(())
 }
@@ -105,8 +146,74 @@ fn test_react() -> Result<()> {
 #[test]
 fn test_emptying_polymer() -> Result<()> {
     let polymer = "aAbB".parse()?;
-    assert_eq!(react(&polymer), "");
-    assert_eq!(react(&polymer).len(), 0);
+    assert_eq!(react(&polymer, &ReactionRules::default()), "");
+    assert_eq!(react(&polymer, &ReactionRules::default()).len(), 0);
     println!("emptying successful!");
     Ok(())
 }
+
+#[test]
+fn test_removing_every_unit_type() -> Result<()> {
+    // Only removing the 'z' units allows the rest of the polymer to react.
+    let polymer = "abZBAz".parse()?;
+    assert_eq!(react(&polymer, &ReactionRules::default()), "abZBAz");
+    assert_eq!(
+        find_shortest_inert_length(&polymer, &ReactionRules::default()),
+        0
+    );
+    Ok(())
+}
+
+#[test]
+fn test_custom_rules() -> Result<()> {
+    let polymer = "aA🧪xX💥bB".parse()?;
+    let rules = "case !x 🧪💥".parse()?;
+    assert_eq!(react(&polymer, &rules), "🧪xX💥");
+    let rules = ReactionRules::none()
+        .with_pair('x', 'X')
+        .with_pair('🧪', '💥');
+    assert_eq!(react(&polymer, &rules), "aAbB");
+    Ok(())
+}
+
+#[test]
+fn test_shortest_inert_length_custom_rules() -> Result<()> {
+    // Removing the 'a's leaves "b", and removing the 'b' leaves "aa". Starting from the reduced
+    // polymer, "a", would wrongly give 0.
+    let polymer = "aab".parse()?;
+    let rules = "ab".parse()?;
+    assert_eq!(react(&polymer, &rules), "a");
+    assert_eq!(find_shortest_inert_length(&polymer, &rules), 1);
+
+    // With both the case and pair rules, 'a' and 'A' are one type, and 'b' and 'B' are another.
+    let polymer = "aBbAab".parse()?;
+    let rules = "case ab".parse()?;
+    assert_eq!(react(&polymer, &rules), "");
+    assert_eq!(find_shortest_inert_length(&polymer, &rules), 1);
+    Ok(())
+}
+
+#[test]
+fn test_reaction_trace() -> Result<()> {
+    let polymer = "dabAcCaCBAcCcaDA".parse()?;
+    let (reduced, trace) = react_with_trace(&polymer, &ReactionRules::default());
+    assert_eq!(reduced, "dabCBAcaDA");
+    assert_eq!(
+        trace,
+        vec![
+            Reaction {
+                positions: (4, 5),
+                units: ('c', 'C'),
+            },
+            Reaction {
+                positions: (3, 6),
+                units: ('A', 'a'),
+            },
+            Reaction {
+                positions: (10, 11),
+                units: ('c', 'C'),
+            },
+        ]
+    );
+    Ok(())
+}
@@ -0,0 +1,142 @@
+use crate::{Error, Result};
+use std::collections::HashSet;
+use std::str::FromStr;
+
+// A unit of a polymer. Units can be any unicode scalar value, not just ascii letters.
+
+pub type Unit = char;
+
+// Decides which pairs of adjacent units react with each other.
+//
+// By default, units react when they are the same letter, but with opposite case. Extra pairs of
+// units can be made to react, and inert units never react, even when another rule says they do.
+
+pub struct ReactionRules {
+    opposite_case: bool,
+    pairs: HashSet<(Unit, Unit)>,
+    inert: HashSet<Unit>,
+}
+
+impl ReactionRules {
+    // Rules where no units react at all.
+
+    pub fn none() -> Self {
+        ReactionRules {
+            opposite_case: false,
+            pairs: HashSet::new(),
+            inert: HashSet::new(),
+        }
+    }
+
+    pub fn with_opposite_case(mut self) -> Self {
+        self.opposite_case = true;
+        self
+    }
+
+    // Makes the two units react with each other, regardless of which one comes first.
+
+    pub fn with_pair(mut self, a: Unit, b: Unit) -> Self {
+        self.pairs.insert((a, b));
+        self.pairs.insert((b, a));
+        self
+    }
+
+    pub fn with_inert(mut self, unit: Unit) -> Self {
+        self.inert.insert(unit);
+        self
+    }
+
+    // Whether these are the default rules, where only units of opposite case react.
+
+    pub fn is_default(&self) -> bool {
+        self.opposite_case && self.pairs.is_empty() && self.inert.is_empty()
+    }
+
+    // Returns the type of the unit, which is what is removed when removing a pair of units. With
+    // the "case" rule, both cases of a letter are one type. Otherwise each unit is its own type,
+    // even if a pair rule makes it react with another unit.
+
+    pub fn unit_type(&self, unit: Unit) -> Unit {
+        if self.opposite_case {
+            unit.to_lowercase().next().unwrap_or(unit)
+        } else {
+            unit
+        }
+    }
+
+    pub fn reacts(&self, a: Unit, b: Unit) -> bool {
+        if self.inert.contains(&a) || self.inert.contains(&b) {
+            return false;
+        }
+        self.pairs.contains(&(a, b))
+            || (self.opposite_case && a != b && a.to_lowercase().eq(b.to_lowercase()))
+    }
+}
+
+impl Default for ReactionRules {
+    fn default() -> Self {
+        ReactionRules::none().with_opposite_case()
+    }
+}
+
+// Parses whitespace separated rules, where:
+//  - "case" makes units of the same letter, but opposite case, react
+//  - a token of two units, eg: "xy", makes those two units react
+//  - a unit prefixed with '!', eg: "!x", makes that unit inert
+
+impl FromStr for ReactionRules {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        s.split_whitespace()
+            .try_fold(ReactionRules::none(), |rules, token| {
+                let units: Vec<Unit> = token.chars().collect();
+                match units.as_slice() {
+                    _ if token == "case" => Ok(rules.with_opposite_case()),
+                    ['!', unit] => Ok(rules.with_inert(*unit)),
+                    [a, b] => Ok(rules.with_pair(*a, *b)),
+                    _ => Err(Error::from(format!("invalid reaction rule: {:?}", token))),
+                }
+            })
+    }
+}
+
+// A single reaction, with the positions of both units within the original polymer.
+
+#[derive(Debug, PartialEq)]
+pub struct Reaction {
+    pub positions: (usize, usize),
+    pub units: (Unit, Unit),
+}
+
+#[test]
+fn test_default_rules() {
+    let rules = ReactionRules::default();
+    assert!(rules.reacts('a', 'A'));
+    assert!(rules.reacts('A', 'a'));
+    assert!(!rules.reacts('a', 'a'));
+    assert!(!rules.reacts('a', 'B'));
+    assert!(rules.reacts('é', 'É'));
+    assert!(rules.reacts('σ', 'Σ'));
+}
+
+#[test]
+fn test_parse_rules() -> Result<()> {
+    let rules = "case 🧪💥 !x".parse::<ReactionRules>()?;
+    assert!(rules.reacts('💥', '🧪'));
+    assert!(rules.reacts('b', 'B'));
+    assert!(!rules.reacts('x', 'X'));
+    assert!(!rules.reacts('a', '🧪'));
+
+    let rules = "ab".parse::<ReactionRules>()?;
+    assert!(rules.reacts('a', 'b'));
+    assert!(!rules.reacts('a', 'A'));
+
+    assert!(!rules.is_default());
+    assert_eq!(rules.unit_type('A'), 'A');
+    assert!(ReactionRules::default().is_default());
+    assert_eq!(ReactionRules::default().unit_type('A'), 'a');
+
+    assert!("abc".parse::<ReactionRules>().is_err());
+    Ok(())
+}
@@ -3,9 +3,17 @@ extern crate lazy_static;
 use std::str::FromStr;
 
 use regex::Regex;
-use std::collections::{HashMap, HashSet};
 use std::io::{Read, Write};
 
+mod metric;
+mod regions;
+mod render;
+
+#[cfg(test)]
+use metric::Manhattan;
+use metric::{parse_metric, Metric};
+use render::render_map;
+
 type Error = std::boxed::Box<dyn std::error::Error>;
 type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -13,52 +21,39 @@ fn main() -> Result<()> {
     let mut input = String::new();
     std::io::stdin().read_to_string(&mut input)?;
     let coords = parse_coordinates(&input)?;
-    let locations = parse_locations(&coords);
+
+    // The metric can optionally be given as the first argument, eg: "chebyshev"
+    let metric_name = std::env::args().nth(1);
+    let metric = parse_metric(metric_name.as_deref().unwrap_or("manhattan"))?;
     writeln!(
         std::io::stdout(),
         "largest finite area size: {}",
-        find_largest_finite_area(&locations, &coords)
+        find_largest_finite_area(&coords, metric.as_ref())
     )?;
     writeln!(
         std::io::stdout(),
         "coord accessible area: {}",
-        find_coord_accessible_area(&locations, 10000)
+        find_coord_accessible_area(&coords, metric.as_ref(), 10000)
     )?;
+
+    // The labelled map can optionally be saved to the file given as the second argument.
+    if let Some(path) = std::env::args().nth(2) {
+        std::fs::write(&path, render_map(&coords, metric.as_ref(), 1, Some(10000))?)?;
+        writeln!(std::io::stdout(), "saved map to: {}", path)?;
+    }
     Ok(())
 }
 
 // Part 1
 
-fn find_largest_finite_area(locations: &Vec<Location>, coords: &Vec<Coordinate>) -> u32 {
-    let bounding_coord_ids = Coordinate::get_bounding_coord_ids(coords, locations);
-    locations
-        .iter()
-        .filter(|location| {
-            if let Some(closest_coordinate_id) = location.closest_coordinate {
-                !bounding_coord_ids.contains(&closest_coordinate_id)
-            } else {
-                false
-            }
-        })
-        .fold(HashMap::<CoordinateId, u32>::new(), |mut map, location| {
-            if let Some(closest_coordinate_id) = location.closest_coordinate {
-                *map.entry(closest_coordinate_id).or_default() += 1;
-            }
-            map
-        })
-        .iter()
-        .max_by_key(|(_, &freq)| freq)
-        .map(|(_, freq)| *freq)
-        .expect("there must be enough locations!")
+fn find_largest_finite_area(coords: &[Coordinate], metric: &dyn Metric) -> u64 {
+    metric.finite_areas(coords).into_values().max().unwrap_or(0)
 }
 
 // Part 2
 
-fn find_coord_accessible_area(locations: &Vec<Location>, limit: u32) -> u32 {
-    locations
-        .iter()
-        .filter(|location| location.total_distance < limit)
-        .count() as u32
+fn find_coord_accessible_area(coords: &[Coordinate], metric: &dyn Metric, limit: u64) -> u64 {
+    metric.count_within(coords, limit)
 }
 
 fn parse_coordinates(input: &str) -> Result<Vec<Coordinate>> {
@@ -67,45 +62,17 @@ fn parse_coordinates(input: &str) -> Result<Vec<Coordinate>> {
         .enumerate()
         .map(|(id, line)| {
             Ok(Coordinate {
-                id: id as CoordinateId,
+                id,
                 point: line.parse()?,
             })
         })
         .collect::<Result<Vec<Coordinate>>>()
 }
 
-#[derive(Debug)]
-struct Location {
-    closest_coordinate: Option<CoordinateId>,
-    total_distance: u32,
-    point: Point,
-}
-
-// Returns locations containing their x,y position, their closest coordinate, and their sum of total
-// distance to all coordinates
-
-fn parse_locations(coords: &Vec<Coordinate>) -> Vec<Location> {
-    let (upper_left, lower_right) = Coordinate::get_grid_bounds(coords);
-    (upper_left.x..=lower_right.x)
-        .flat_map(|x| {
-            (upper_left.y..=lower_right.y).map(move |y| {
-                let point = Point { x, y };
-                Location {
-                    closest_coordinate: point
-                        .get_closest_coordinate(coords)
-                        .and_then(|coordinate| Some(coordinate.id)),
-                    total_distance: point.get_sum_distance(coords),
-                    point,
-                }
-            })
-        })
-        .collect::<Vec<Location>>()
-}
-
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 struct Point {
-    x: u32,
-    y: u32,
+    x: i64,
+    y: i64,
 }
 
 impl FromStr for Point {
@@ -130,50 +97,7 @@ impl FromStr for Point {
     }
 }
 
-impl Point {
-    // Returns the coordinate that is closest to this point.
-    // If more than one coordinate is tied for being closer, returns None
-    fn get_closest_coordinate<'a>(&self, coords: &'a Vec<Coordinate>) -> Option<&'a Coordinate> {
-        let mut closest_coord = None;
-        let mut shortest_distance = std::u32::MAX;
-        for coord in coords {
-            let distance = self.get_distance(&coord.point);
-            if distance < shortest_distance {
-                shortest_distance = distance;
-                closest_coord = Some(coord);
-            } else if distance == shortest_distance {
-                closest_coord = None;
-            }
-        }
-        closest_coord
-    }
-
-    // Returns the Manhattan Distance between this Point and another Point
-    fn get_distance(&self, other: &Point) -> u32 {
-        let d_x = if self.x > other.x {
-            self.x.saturating_sub(other.x)
-        } else {
-            other.x.saturating_sub(self.x)
-        };
-        let d_y = if self.y > other.y {
-            self.y.saturating_sub(other.y)
-        } else {
-            other.y.saturating_sub(self.y)
-        };
-
-        d_x.saturating_add(d_y)
-    }
-
-    // Returns the sum of the Manhattan Distance between this Point and all of the Coordinates
-    fn get_sum_distance(&self, coords: &Vec<Coordinate>) -> u32 {
-        coords
-            .iter()
-            .map(|coord| self.get_distance(&coord.point))
-            .sum()
-    }
-}
-
-type CoordinateId = u8;
+type CoordinateId = usize;
 
 struct Coordinate {
     id: CoordinateId,
@@ -182,9 +106,9 @@ struct Coordinate {
 
 impl Coordinate {
     // Returns a tuple representing the top-left, and bottom-right of the grid.
-    fn get_grid_bounds(coords: &Vec<Coordinate>) -> (Point, Point) {
+    fn get_grid_bounds(coords: &[Coordinate]) -> (Point, Point) {
         let (min_x, min_y, max_x, max_y) = coords.iter().fold(
-            (std::u32::MAX, std::u32::MAX, 0, 0),
+            (i64::MAX, i64::MAX, i64::MIN, i64::MIN),
             |(min_x, min_y, max_x, max_y), coord| {
                 (
                     std::cmp::min(min_x, coord.point.x),
@@ -197,26 +121,6 @@ impl Coordinate {
 
         (Point { x: min_x, y: min_y }, Point { x: max_x, y: max_y })
     }
-
-    fn get_bounding_coord_ids(
-        coords: &Vec<Coordinate>,
-        locations: &Vec<Location>,
-    ) -> HashSet<CoordinateId> {
-        let (upper_left, lower_right) = Coordinate::get_grid_bounds(coords);
-        locations.iter().fold(HashSet::new(), |mut set, location| {
-            let point = &location.point;
-            if point.x == upper_left.x
-                || point.x == lower_right.x
-                || point.y == upper_left.y
-                || point.y == lower_right.y
-            {
-                if let Some(closest_coordinate_id) = location.closest_coordinate {
-                    set.insert(closest_coordinate_id);
-                }
-            }
-            set
-        })
-    }
 }
 
 #[test]
@@ -229,9 +133,8 @@ fn test_find_largest_finite_area() -> Result<()> {
         5, 5\n\
         8, 9\
     ";
-    let coords = parse_coordinates(&s)?;
-    let locations = parse_locations(&coords);
-    assert_eq!(find_largest_finite_area(&locations, &coords), 17);
+    let coords = parse_coordinates(s)?;
+    assert_eq!(find_largest_finite_area(&coords, &Manhattan), 17);
     println!("find_largest_finite_area passed!");
     Ok(())
 }
@@ -246,9 +149,8 @@ fn test_find_coord_accessible_area() -> Result<()> {
         5, 5\n\
         8, 9\
     ";
-    let coords = parse_coordinates(&s)?;
-    let locations = parse_locations(&coords);
-    assert_eq!(find_coord_accessible_area(&locations, 32), 16);
+    let coords = parse_coordinates(s)?;
+    assert_eq!(find_coord_accessible_area(&coords, &Manhattan, 32), 16);
     println!("coord_accessible_area passed!");
     Ok(())
 }
@@ -0,0 +1,906 @@
+use crate::regions::{get_sum_distances, RegionMap, SafeRegion};
+use crate::{Coordinate, CoordinateId, Error, Point, Result};
+use std::collections::{HashMap, HashSet};
+
+// A way of measuring the distance between two points.
+//
+// Besides the distance itself, each metric knows which coordinates have a region containing
+// infinitely many locations, and how far the finite regions can reach, since both depend on the
+// shape of the metric's circles.
+
+pub trait Metric {
+    fn distance(&self, a: &Point, b: &Point) -> f64;
+
+    // Returns the ids of the coordinates whose regions are infinite.
+    fn infinite_regions(&self, coords: &[Coordinate]) -> HashSet<CoordinateId>;
+
+    // Returns the top-left and bottom-right corners of a box containing every finite region.
+    fn finite_bounds(&self, coords: &[Coordinate]) -> (Point, Point);
+
+    // Returns the coordinate that is closest to this point.
+    // If more than one coordinate is tied for being closer, returns None
+    fn get_closest_coordinate<'a>(
+        &self,
+        point: &Point,
+        coords: &'a [Coordinate],
+    ) -> Option<&'a Coordinate> {
+        let mut closest_coord = None;
+        let mut shortest_distance = f64::INFINITY;
+        for coord in coords {
+            let distance = self.distance(point, &coord.point);
+            if distance < shortest_distance {
+                shortest_distance = distance;
+                closest_coord = Some(coord);
+            } else if distance == shortest_distance {
+                closest_coord = None;
+            }
+        }
+        closest_coord
+    }
+
+    // Returns the sum of the distances between this Point and all of the Coordinates
+    fn get_sum_distance(&self, point: &Point, coords: &[Coordinate]) -> f64 {
+        coords
+            .iter()
+            .map(|coord| self.distance(point, &coord.point))
+            .sum()
+    }
+
+    // Labels every location within the bounds with its closest coordinate. By default, this
+    // checks the distance to every coordinate, for every location.
+    fn label_regions(&self, coords: &[Coordinate], bounds: (Point, Point)) -> RegionMap {
+        RegionMap::from_fn(bounds, |point| {
+            self.get_closest_coordinate(point, coords)
+                .map(|coord| coord.id)
+        })
+    }
+
+    // Returns the number of locations owned by each coordinate with a finite region. By default,
+    // this labels every location within the finite bounds.
+    fn finite_areas(&self, coords: &[Coordinate]) -> HashMap<CoordinateId, u64> {
+        let infinite = self.infinite_regions(coords);
+        let mut areas = self
+            .label_regions(coords, self.finite_bounds(coords))
+            .areas();
+        areas.retain(|id, _| !infinite.contains(id));
+        areas
+    }
+
+    // Returns a function giving the total distance from a location within the bounds to all of
+    // the coordinates. By default, this sums the distances to every coordinate, each time.
+    fn total_distance<'a>(
+        &'a self,
+        coords: &'a [Coordinate],
+        _bounds: &(Point, Point),
+    ) -> Box<dyn Fn(&Point) -> f64 + 'a> {
+        Box::new(move |point| self.get_sum_distance(point, coords))
+    }
+
+    // Returns the locations whose total distance to all of the coordinates is less than the limit.
+    //
+    // A location that is `d` steps outside of the bounding box is at least `d` away from every
+    // coordinate, under any of our metrics, so the region can't reach further than `limit / n`
+    // steps past the bounding box, where `n` is the number of coordinates.
+    fn safe_region(&self, coords: &[Coordinate], limit: u64) -> SafeRegion {
+        let (upper_left, lower_right) = Coordinate::get_grid_bounds(coords);
+        let margin = limit as i64 / coords.len().max(1) as i64 + 1;
+        let bounds = (
+            Point {
+                x: upper_left.x - margin,
+                y: upper_left.y - margin,
+            },
+            Point {
+                x: lower_right.x + margin,
+                y: lower_right.y + margin,
+            },
+        );
+        let total_distance = self.total_distance(coords, &bounds);
+        SafeRegion::find(coords, bounds, limit, total_distance)
+    }
+
+    // Counts the locations whose total distance to all of the coordinates is less than the limit.
+    fn count_within(&self, coords: &[Coordinate], limit: u64) -> u64 {
+        self.safe_region(coords, limit).count()
+    }
+}
+
+pub fn parse_metric(name: &str) -> Result<Box<dyn Metric>> {
+    match name {
+        "manhattan" => Ok(Box::new(Manhattan)),
+        "chebyshev" => Ok(Box::new(Chebyshev)),
+        "euclidean" => Ok(Box::new(Euclidean)),
+        _ => Err(Error::from(format!("unknown metric: {}", name))),
+    }
+}
+
+pub struct Manhattan;
+
+impl Metric for Manhattan {
+    fn distance(&self, a: &Point, b: &Point) -> f64 {
+        ((a.x - b.x).abs() + (a.y - b.y).abs()) as f64
+    }
+
+    // Past the right edge of the bounding box, stepping further right moves a point one unit
+    // further away from every coordinate, so the closest coordinate never changes. The same goes
+    // for the other edges, and for the corners. This means that a region is infinite exactly when
+    // it owns a location on the ring just outside the bounding box.
+
+    fn infinite_regions(&self, coords: &[Coordinate]) -> HashSet<CoordinateId> {
+        let (upper_left, lower_right) = Coordinate::get_grid_bounds(coords);
+        let ring = get_ring(&upper_left, &lower_right, 1);
+        owners(self, ring, coords)
+    }
+
+    // By the same argument, every location outside of the bounding box is in an infinite region.
+
+    fn finite_bounds(&self, coords: &[Coordinate]) -> (Point, Point) {
+        Coordinate::get_grid_bounds(coords)
+    }
+
+    fn label_regions(&self, coords: &[Coordinate], bounds: (Point, Point)) -> RegionMap {
+        RegionMap::flood_fill(coords, bounds, &[(1, 0), (-1, 0), (0, 1), (0, -1)])
+    }
+
+    // The total distance is the sum of the total x distance and the total y distance, which can
+    // each be computed once per column and row, rather than once per location.
+
+    fn total_distance<'a>(
+        &'a self,
+        coords: &'a [Coordinate],
+        (upper_left, lower_right): &(Point, Point),
+    ) -> Box<dyn Fn(&Point) -> f64 + 'a> {
+        let xs: Vec<i64> = coords.iter().map(|coord| coord.point.x).collect();
+        let ys: Vec<i64> = coords.iter().map(|coord| coord.point.y).collect();
+        let x_sums = get_sum_distances(&xs, upper_left.x, lower_right.x);
+        let y_sums = get_sum_distances(&ys, upper_left.y, lower_right.y);
+        let (min_x, min_y) = (upper_left.x, upper_left.y);
+        Box::new(move |point| {
+            (x_sums[(point.x - min_x) as usize] + y_sums[(point.y - min_y) as usize]) as f64
+        })
+    }
+}
+
+pub struct Chebyshev;
+
+impl Chebyshev {
+    // Rotates a point by 45 degrees, where the Chebyshev distance between two points is half of
+    // the Manhattan distance between their rotated points. Only rotated points where u and v have
+    // the same parity map back onto a location.
+
+    fn rotate(point: &Point) -> Point {
+        Point {
+            x: point.x + point.y,
+            y: point.x - point.y,
+        }
+    }
+
+    fn unrotate(point: &Point) -> Option<Point> {
+        if (point.x + point.y) % 2 != 0 {
+            return None;
+        }
+        Some(Point {
+            x: (point.x + point.y) / 2,
+            y: (point.x - point.y) / 2,
+        })
+    }
+
+    fn rotated_bounds(coords: &[Coordinate]) -> (Point, Point) {
+        let rotated: Vec<Coordinate> = coords
+            .iter()
+            .map(|coord| Coordinate {
+                id: coord.id,
+                point: Chebyshev::rotate(&coord.point),
+            })
+            .collect();
+        Coordinate::get_grid_bounds(&rotated)
+    }
+}
+
+impl Metric for Chebyshev {
+    fn distance(&self, a: &Point, b: &Point) -> f64 {
+        (a.x - b.x).abs().max((a.y - b.y).abs()) as f64
+    }
+
+    // Once rotated, this is the same argument as for the Manhattan distance. Rotated points only
+    // map back onto locations every other step, so the ring needs to be two steps wide.
+
+    fn infinite_regions(&self, coords: &[Coordinate]) -> HashSet<CoordinateId> {
+        let (upper_left, lower_right) = Chebyshev::rotated_bounds(coords);
+        let ring = get_ring(&upper_left, &lower_right, 1)
+            .chain(get_ring(&upper_left, &lower_right, 2))
+            .filter_map(|point| Chebyshev::unrotate(&point));
+        owners(self, ring, coords)
+    }
+
+    // Every finite region is within the rotated bounding box, which is a diamond once rotated
+    // back, so we return the box around that diamond.
+
+    fn finite_bounds(&self, coords: &[Coordinate]) -> (Point, Point) {
+        let (upper_left, lower_right) = Chebyshev::rotated_bounds(coords);
+        (
+            Point {
+                x: (upper_left.x + upper_left.y).div_euclid(2),
+                y: (upper_left.x - lower_right.y).div_euclid(2),
+            },
+            Point {
+                x: (lower_right.x + lower_right.y + 1).div_euclid(2),
+                y: (lower_right.x - upper_left.y + 1).div_euclid(2),
+            },
+        )
+    }
+
+    fn label_regions(&self, coords: &[Coordinate], bounds: (Point, Point)) -> RegionMap {
+        let steps = [
+            (1, 0),
+            (-1, 0),
+            (0, 1),
+            (0, -1),
+            (1, 1),
+            (1, -1),
+            (-1, 1),
+            (-1, -1),
+        ];
+        RegionMap::flood_fill(coords, bounds, &steps)
+    }
+
+    // Once rotated, the total distance is half of the sum of the total u and v distances, like
+    // for the Manhattan distance.
+
+    fn total_distance<'a>(
+        &'a self,
+        coords: &'a [Coordinate],
+        (upper_left, lower_right): &(Point, Point),
+    ) -> Box<dyn Fn(&Point) -> f64 + 'a> {
+        let us: Vec<i64> = coords.iter().map(|c| c.point.x + c.point.y).collect();
+        let vs: Vec<i64> = coords.iter().map(|c| c.point.x - c.point.y).collect();
+        let (min_u, max_u) = (upper_left.x + upper_left.y, lower_right.x + lower_right.y);
+        let (min_v, max_v) = (upper_left.x - lower_right.y, lower_right.x - upper_left.y);
+        let u_sums = get_sum_distances(&us, min_u, max_u);
+        let v_sums = get_sum_distances(&vs, min_v, max_v);
+        Box::new(move |point| {
+            let u = (point.x + point.y - min_u) as usize;
+            let v = (point.x - point.y - min_v) as usize;
+            (u_sums[u] + v_sums[v]) as f64 / 2.0
+        })
+    }
+}
+
+// The total Euclidean distance doesn't split into x and y parts, so finding the safe region
+// evaluates the distance to every coordinate a few times per column, rather than once per location.
+
+pub struct Euclidean;
+
+impl Metric for Euclidean {
+    fn distance(&self, a: &Point, b: &Point) -> f64 {
+        (((a.x - b.x).pow(2) + (a.y - b.y).pow(2)) as f64).sqrt()
+    }
+
+    // Far away in any direction, the closest coordinate is the one furthest along that
+    // direction, so the infinite regions are those of the coordinates on the convex hull. This
+    // includes coordinates lying on an edge of the hull, which own a strip of locations leading
+    // away from the hull. Coordinates sharing the same point are always tied, and own nothing.
+
+    fn infinite_regions(&self, coords: &[Coordinate]) -> HashSet<CoordinateId> {
+        let hull = get_convex_hull(coords);
+        unique_coords(coords)
+            .filter(|coord| hull.iter().any(|(a, b)| is_on_segment(&coord.point, a, b)))
+            .map(|coord| coord.id)
+            .collect()
+    }
+
+    fn finite_bounds(&self, coords: &[Coordinate]) -> (Point, Point) {
+        let sites = SiteGrid::new(coords);
+        let (mut upper_left, mut lower_right) = Coordinate::get_grid_bounds(coords);
+        for cell in get_finite_cells(coords, &sites) {
+            for (x, y) in cell.polygon {
+                upper_left.x = upper_left.x.min(x.floor() as i64);
+                upper_left.y = upper_left.y.min(y.floor() as i64);
+                lower_right.x = lower_right.x.max(x.ceil() as i64);
+                lower_right.y = lower_right.y.max(y.ceil() as i64);
+            }
+        }
+        (upper_left, lower_right)
+    }
+
+    // Labels each location with the coordinate that has the smallest squared distance, which is
+    // exact, looking only at the coordinates near the location.
+
+    fn label_regions(&self, coords: &[Coordinate], bounds: (Point, Point)) -> RegionMap {
+        let sites = SiteGrid::new(coords);
+        RegionMap::from_fn(bounds, |point| sites.closest(point))
+    }
+
+    // Finite regions near the hull can reach far outside of the bounding box, so labelling every
+    // location within the finite bounds would take too long. Instead, each column of a region is
+    // a single run of locations, which is found from the region's polygon. Locations close to the
+    // polygon's edges may be tied, so the ends of each run are checked exactly, against the
+    // region's neighbours.
+
+    fn finite_areas(&self, coords: &[Coordinate]) -> HashMap<CoordinateId, u64> {
+        const EPSILON: f64 = 1e-6;
+        let sites = SiteGrid::new(coords);
+        let mut areas = HashMap::new();
+        for cell in get_finite_cells(coords, &sites) {
+            let xs = cell.polygon.iter().map(|&(x, _)| x);
+            let min_x = xs.clone().fold(f64::INFINITY, f64::min);
+            let max_x = xs.fold(f64::NEG_INFINITY, f64::max);
+            let mut area = 0;
+            for x in (min_x - EPSILON).ceil() as i64..=(max_x + EPSILON).floor() as i64 {
+                let (top, bottom) = get_column(&cell.polygon, (x as f64).max(min_x).min(max_x));
+                let is_owned = |y| cell.owns(&Point { x, y });
+                let mut top = (top - EPSILON).ceil() as i64;
+                let mut bottom = (bottom + EPSILON).floor() as i64;
+                while top <= bottom && !is_owned(top) {
+                    top += 1;
+                }
+                while top <= bottom && !is_owned(bottom) {
+                    bottom -= 1;
+                }
+                area += (bottom - top + 1).max(0) as u64;
+            }
+            if area > 0 {
+                areas.insert(cell.coord.id, area);
+            }
+        }
+        areas
+    }
+}
+
+// Returns the exact (convex) polygon of each finite region, which is the intersection of the
+// half-planes closer to its coordinate than to each other coordinate. Each is found by clipping a
+// square known to contain the region with each of those half-planes.
+//
+// To find that first square: for a coordinate `s` inside the hull, any direction has a hull
+// vertex `t` at least `h` further along it, where `h` is the distance from `s` to the closest
+// hull edge. Then a point `p` at distance `r` from `s` is closer to `t` once
+// `r > |t - s|^2 / 2h`, so the region fits in a circle of radius `diameter^2 / 2h`.
+//
+// The other coordinates are visited from the nearest outwards. Once the polygon is within `r`
+// of `s`, a coordinate more than `2r` away can't clip it, so the rest are skipped.
+
+fn get_finite_cells<'a>(coords: &'a [Coordinate], sites: &SiteGrid<'a>) -> Vec<FiniteCell<'a>> {
+    let hull = get_convex_hull(coords);
+    let diameter_sq = hull
+        .iter()
+        .flat_map(|(a, _)| hull.iter().map(move |(b, _)| squared_length(a, b)))
+        .max()
+        .unwrap_or(0) as f64;
+    let infinite = Euclidean.infinite_regions(coords);
+    unique_coords(coords)
+        .filter(|coord| !infinite.contains(&coord.id))
+        .map(|coord| {
+            let s = &coord.point;
+            let h = hull
+                .iter()
+                .map(|(a, b)| cross(a, b, s).abs() as f64 / (squared_length(a, b) as f64).sqrt())
+                .fold(f64::INFINITY, f64::min);
+            let r = diameter_sq / (2.0 * h) + 1.0;
+            let (x, y) = (s.x as f64, s.y as f64);
+            let mut neighbours = vec![];
+            let mut cell = vec![
+                (x - r, y - r),
+                (x + r, y - r),
+                (x + r, y + r),
+                (x - r, y + r),
+            ];
+            for ring in 0.. {
+                let reach = cell
+                    .iter()
+                    .map(|&(px, py)| (px - x).hypot(py - y))
+                    .fold(0.0, f64::max);
+                if !sites.ring_may_reach(s, ring, 2.0 * reach) {
+                    break;
+                }
+                for other in sites.ring(s, ring).filter(|other| other.point != *s) {
+                    let (clipped, reached) = clip_to_bisector(cell, s, &other.point);
+                    cell = clipped;
+                    // Lattice points past a bisector reach at least 0.5 past it, so this allows
+                    // for rounding while never missing a bisector that cuts off a location.
+                    if reached > -0.25 {
+                        neighbours.push(&other.point);
+                    }
+                }
+            }
+            FiniteCell {
+                coord,
+                polygon: cell,
+                neighbours,
+            }
+        })
+        .collect()
+}
+
+// The region of a coordinate, along with the coordinates whose bisectors bound it. A location is
+// in the region exactly when it is closer to the coordinate than to each of those neighbours.
+
+struct FiniteCell<'a> {
+    coord: &'a Coordinate,
+    polygon: Vec<(f64, f64)>,
+    neighbours: Vec<&'a Point>,
+}
+
+impl FiniteCell<'_> {
+    fn owns(&self, point: &Point) -> bool {
+        let distance = squared_length(point, &self.coord.point);
+        self.neighbours
+            .iter()
+            .all(|neighbour| distance < squared_length(point, neighbour))
+    }
+}
+
+// Returns the smallest and largest y where a convex polygon crosses the vertical line at x.
+
+fn get_column(polygon: &[(f64, f64)], x: f64) -> (f64, f64) {
+    let mut column = (f64::INFINITY, f64::NEG_INFINITY);
+    for (i, &(ax, ay)) in polygon.iter().enumerate() {
+        let (bx, by) = polygon[(i + 1) % polygon.len()];
+        let ys = if ax == bx && ax == x {
+            (ay.min(by), ay.max(by))
+        } else if (ax < x && x <= bx) || (bx < x && x <= ax) || x == ax {
+            let y = if x == ax {
+                ay
+            } else {
+                ay + (x - ax) / (bx - ax) * (by - ay)
+            };
+            (y, y)
+        } else {
+            continue;
+        };
+        column = (column.0.min(ys.0), column.1.max(ys.1));
+    }
+    column
+}
+
+// The coordinates sorted into square buckets, about one coordinate per bucket, so that the
+// coordinates near a point can be found without looking at all of them.
+
+struct SiteGrid<'a> {
+    origin: Point,
+    side: i64,
+    width: i64,
+    height: i64,
+    buckets: Vec<Vec<&'a Coordinate>>,
+}
+
+impl<'a> SiteGrid<'a> {
+    fn new(coords: &'a [Coordinate]) -> Self {
+        if coords.is_empty() {
+            return SiteGrid {
+                origin: Point { x: 0, y: 0 },
+                side: 1,
+                width: 0,
+                height: 0,
+                buckets: vec![],
+            };
+        }
+        let (upper_left, lower_right) = Coordinate::get_grid_bounds(coords);
+        let area = ((lower_right.x - upper_left.x + 1) * (lower_right.y - upper_left.y + 1)) as f64;
+        let side = ((area / coords.len().max(1) as f64).sqrt().ceil() as i64).max(1);
+        let width = (lower_right.x - upper_left.x) / side + 1;
+        let height = (lower_right.y - upper_left.y) / side + 1;
+        let mut grid = SiteGrid {
+            origin: upper_left,
+            side,
+            width,
+            height,
+            buckets: vec![vec![]; (width * height) as usize],
+        };
+        for coord in coords {
+            let (bx, by) = grid.bucket(&coord.point);
+            grid.buckets[(by * width + bx) as usize].push(coord);
+        }
+        grid
+    }
+
+    fn bucket(&self, point: &Point) -> (i64, i64) {
+        (
+            (point.x - self.origin.x).div_euclid(self.side),
+            (point.y - self.origin.y).div_euclid(self.side),
+        )
+    }
+
+    // Whether any coordinate in the ring of buckets `ring` steps away from the point's bucket
+    // could be within the distance. A coordinate in that ring is more than `(ring - 1) * side`
+    // away, along x or y. Rings past the edge of the grid are empty.
+
+    fn ring_may_reach(&self, point: &Point, ring: i64, distance: f64) -> bool {
+        let (bx, by) = self.bucket(point);
+        let furthest = [bx, self.width - 1 - bx, by, self.height - 1 - by]
+            .iter()
+            .fold(0, |furthest, &d| furthest.max(d));
+        ring <= furthest && ((ring - 1) * self.side) as f64 <= distance
+    }
+
+    // Returns the coordinates in the buckets exactly `ring` steps away from the point's bucket.
+
+    fn ring<'g>(&'g self, point: &Point, ring: i64) -> impl Iterator<Item = &'a Coordinate> + 'g {
+        let (bx, by) = self.bucket(point);
+        let (min_x, max_x) = ((bx - ring).max(0), (bx + ring).min(self.width - 1));
+        let (min_y, max_y) = ((by - ring).max(0), (by + ring).min(self.height - 1));
+        (min_y..=max_y)
+            .flat_map(move |y| {
+                // Only the first and last buckets of the rows between the top and bottom of the
+                // ring are on it.
+                let xs: Box<dyn Iterator<Item = i64>> = if y == by - ring || y == by + ring {
+                    Box::new(min_x..=max_x)
+                } else {
+                    Box::new(
+                        vec![bx - ring, bx + ring]
+                            .into_iter()
+                            .filter(move |&x| x >= min_x && x <= max_x),
+                    )
+                };
+                xs.map(move |x| (x, y))
+            })
+            .flat_map(move |(x, y)| self.buckets[(y * self.width + x) as usize].iter().copied())
+    }
+
+    // Returns the id of the coordinate closest to the point, or None if several are tied, searching
+    // the rings of buckets outwards until no further ring can hold anything as close. Rings that
+    // don't reach the grid yet are skipped.
+
+    fn closest(&self, point: &Point) -> Option<CoordinateId> {
+        let (bx, by) = self.bucket(point);
+        let first = [-bx, bx - (self.width - 1), -by, by - (self.height - 1)]
+            .iter()
+            .fold(0, |first, &d| first.max(d));
+        let mut closest = None;
+        let mut shortest = i64::MAX;
+        for ring in first.. {
+            if !self.ring_may_reach(point, ring, (shortest as f64).sqrt()) {
+                break;
+            }
+            for coord in self.ring(point, ring) {
+                let distance = squared_length(point, &coord.point);
+                if distance < shortest {
+                    shortest = distance;
+                    closest = Some(coord.id);
+                } else if distance == shortest {
+                    closest = None;
+                }
+            }
+        }
+        closest
+    }
+}
+
+// Clips a convex polygon to the half-plane of points at least as close to `s` as to `t`, which are
+// the points `p` where `2p.(t - s) <= |t|^2 - |s|^2`. Also returns how far past the bisector the
+// polygon reached, in the same units, which is negative if it didn't reach it at all.
+
+fn clip_to_bisector(polygon: Vec<(f64, f64)>, s: &Point, t: &Point) -> (Vec<(f64, f64)>, f64) {
+    let (nx, ny) = ((t.x - s.x) as f64, (t.y - s.y) as f64);
+    let limit = ((t.x.pow(2) + t.y.pow(2)) - (s.x.pow(2) + s.y.pow(2))) as f64 / 2.0;
+    let excess = |(x, y): (f64, f64)| x * nx + y * ny - limit;
+
+    let mut clipped = vec![];
+    let mut reached = f64::NEG_INFINITY;
+    for (i, &a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        let (ea, eb) = (excess(a), excess(b));
+        reached = reached.max(ea);
+        if ea <= 0.0 {
+            clipped.push(a);
+        }
+        if (ea < 0.0 && eb > 0.0) || (ea > 0.0 && eb < 0.0) {
+            let t = ea / (ea - eb);
+            clipped.push((a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1)));
+        }
+    }
+    (clipped, reached)
+}
+
+// Returns the locations on the edge of the box that is `margin` steps larger than the given box.
+
+fn get_ring(upper_left: &Point, lower_right: &Point, margin: i64) -> impl Iterator<Item = Point> {
+    let (min_x, min_y) = (upper_left.x - margin, upper_left.y - margin);
+    let (max_x, max_y) = (lower_right.x + margin, lower_right.y + margin);
+    let horizontal =
+        (min_x..=max_x).flat_map(move |x| vec![Point { x, y: min_y }, Point { x, y: max_y }]);
+    let vertical =
+        (min_y..=max_y).flat_map(move |y| vec![Point { x: min_x, y }, Point { x: max_x, y }]);
+    horizontal.chain(vertical)
+}
+
+fn owners(
+    metric: &dyn Metric,
+    points: impl Iterator<Item = Point>,
+    coords: &[Coordinate],
+) -> HashSet<CoordinateId> {
+    points
+        .filter_map(|point| metric.get_closest_coordinate(&point, coords))
+        .map(|coord| coord.id)
+        .collect()
+}
+
+// Coordinates that don't share their point with any other coordinate.
+
+fn unique_coords(coords: &[Coordinate]) -> impl Iterator<Item = &Coordinate> {
+    let mut counts: HashMap<(i64, i64), u32> = HashMap::new();
+    for coord in coords {
+        *counts.entry((coord.point.x, coord.point.y)).or_default() += 1;
+    }
+    coords
+        .iter()
+        .filter(move |coord| counts[&(coord.point.x, coord.point.y)] == 1)
+}
+
+// The z component of the cross product of (b - a) and (c - a), which is positive when a, b, c
+// turn counter-clockwise, and zero when they are collinear.
+
+fn cross(a: &Point, b: &Point, c: &Point) -> i64 {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+fn squared_length(a: &Point, b: &Point) -> i64 {
+    (a.x - b.x).pow(2) + (a.y - b.y).pow(2)
+}
+
+fn is_on_segment(p: &Point, a: &Point, b: &Point) -> bool {
+    cross(a, b, p) == 0
+        && p.x >= a.x.min(b.x)
+        && p.x <= a.x.max(b.x)
+        && p.y >= a.y.min(b.y)
+        && p.y <= a.y.max(b.y)
+}
+
+// Returns the edges of the convex hull, using Andrew's monotone chain algorithm. When all of the
+// points are collinear, the "hull" is the segment between the two extreme points.
+
+fn get_convex_hull(coords: &[Coordinate]) -> Vec<(Point, Point)> {
+    let mut points: Vec<(i64, i64)> = coords
+        .iter()
+        .map(|coord| (coord.point.x, coord.point.y))
+        .collect();
+    points.sort_unstable();
+    points.dedup();
+    let points: Vec<Point> = points.into_iter().map(|(x, y)| Point { x, y }).collect();
+    if points.len() < 3 {
+        return match (points.first(), points.last()) {
+            (Some(a), Some(b)) => vec![(a.clone(), b.clone())],
+            _ => vec![],
+        };
+    }
+
+    let mut hull: Vec<Point> = vec![];
+    for pass in 0..2 {
+        let start = hull.len();
+        let iter: Box<dyn Iterator<Item = &Point>> = if pass == 0 {
+            Box::new(points.iter())
+        } else {
+            Box::new(points.iter().rev())
+        };
+        for point in iter {
+            while hull.len() >= start + 2
+                && cross(&hull[hull.len() - 2], &hull[hull.len() - 1], point) <= 0
+            {
+                hull.pop();
+            }
+            hull.push(point.clone());
+        }
+        // The last point of each pass is the first point of the next one.
+        hull.pop();
+    }
+    (0..hull.len())
+        .map(|i| (hull[i].clone(), hull[(i + 1) % hull.len()].clone()))
+        .collect()
+}
+
+#[cfg(test)]
+fn example_coords() -> Vec<Coordinate> {
+    crate::parse_coordinates("1, 1\n1, 6\n8, 3\n3, 4\n5, 5\n8, 9").unwrap()
+}
+
+// Checks a metric against a brute-force scan of a much larger box: the owners of the edge of that
+// box should be the infinite regions, and no finite region should reach outside of its bounds.
+
+#[cfg(test)]
+fn check_against_brute_force(metric: &dyn Metric, coords: &[Coordinate]) {
+    let infinite = metric.infinite_regions(coords);
+    let (upper_left, lower_right) = Coordinate::get_grid_bounds(coords);
+    let margin = 300;
+    let edge = get_ring(&upper_left, &lower_right, margin);
+    assert_eq!(owners(metric, edge, coords), infinite);
+
+    let (min, max) = metric.finite_bounds(coords);
+    for x in upper_left.x - margin..=lower_right.x + margin {
+        for y in upper_left.y - margin..=lower_right.y + margin {
+            let point = Point { x, y };
+            if (min.x..=max.x).contains(&x) && (min.y..=max.y).contains(&y) {
+                continue;
+            }
+            if let Some(coord) = metric.get_closest_coordinate(&point, coords) {
+                assert!(
+                    infinite.contains(&coord.id),
+                    "{:?} escapes its bounds",
+                    point
+                );
+            }
+        }
+    }
+}
+
+// Checks the overridden region labelling and counting against the default implementations.
+
+#[cfg(test)]
+fn check_against_defaults(metric: &dyn Metric, coords: &[Coordinate]) {
+    struct Default<'m>(&'m dyn Metric);
+    impl Metric for Default<'_> {
+        fn distance(&self, a: &Point, b: &Point) -> f64 {
+            self.0.distance(a, b)
+        }
+        fn infinite_regions(&self, coords: &[Coordinate]) -> HashSet<CoordinateId> {
+            self.0.infinite_regions(coords)
+        }
+        fn finite_bounds(&self, coords: &[Coordinate]) -> (Point, Point) {
+            self.0.finite_bounds(coords)
+        }
+    }
+
+    assert_eq!(
+        metric.finite_areas(coords),
+        Default(metric).finite_areas(coords)
+    );
+    let bounds = metric.finite_bounds(coords);
+    let labels = metric.label_regions(coords, bounds.clone());
+    let expected = Default(metric).label_regions(coords, bounds.clone());
+    for x in bounds.0.x..=bounds.1.x {
+        for y in bounds.0.y..=bounds.1.y {
+            let point = Point { x, y };
+            assert_eq!(labels.get(&point), expected.get(&point), "at {:?}", point);
+        }
+    }
+    for limit in &[0, 1, 10, 32, 50, 100] {
+        let (upper_left, lower_right) = Coordinate::get_grid_bounds(coords);
+        let margin = *limit as i64 / coords.len() as i64 + 1;
+        let expected = (upper_left.x - margin..=lower_right.x + margin)
+            .flat_map(|x| (upper_left.y - margin..=lower_right.y + margin).map(move |y| (x, y)))
+            .filter(|&(x, y)| metric.get_sum_distance(&Point { x, y }, coords) < *limit as f64)
+            .count() as u64;
+        assert_eq!(
+            metric.count_within(coords, *limit),
+            expected,
+            "with limit {}",
+            limit
+        );
+    }
+}
+
+// Checks a metric on 10,000 coordinates spread over a box of a million locations, by comparing
+// samples of its labels and its safe region against the distances to every coordinate. Looking at
+// every coordinate for every location would take far too long.
+
+#[cfg(test)]
+fn check_at_scale(metric: &dyn Metric) {
+    let mut seed: u64 = 12345;
+    let mut random = |n: i64| {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((seed >> 33) % n as u64) as i64
+    };
+    let coords: Vec<Coordinate> = (0..10_000)
+        .map(|id| Coordinate {
+            id,
+            point: Point {
+                x: random(1000),
+                y: random(1000),
+            },
+        })
+        .collect();
+
+    let bounds = (Point { x: 0, y: 0 }, Point { x: 999, y: 999 });
+    let labels = metric.label_regions(&coords, bounds.clone());
+    for _ in 0..500 {
+        let point = Point {
+            x: random(1000),
+            y: random(1000),
+        };
+        let expected = metric
+            .get_closest_coordinate(&point, &coords)
+            .map(|coord| coord.id);
+        assert_eq!(labels.get(&point), expected, "at {:?}", point);
+    }
+
+    // Checks the areas of a few regions in the middle, which are well within the box.
+    let areas = metric.finite_areas(&coords);
+    let infinite = metric.infinite_regions(&coords);
+    let labelled_areas = labels.areas();
+    assert_eq!(areas.len() + infinite.len(), labelled_areas.len());
+    let middle = coords
+        .iter()
+        .filter(|coord| (400..600).contains(&coord.point.x) && (400..600).contains(&coord.point.y));
+    for coord in middle.take(20) {
+        assert_eq!(
+            areas.get(&coord.id).copied(),
+            labelled_areas.get(&coord.id).copied(),
+            "area of {:?}",
+            coord.point
+        );
+    }
+
+    // A limit that makes a safe region of a few thousand locations around the middle.
+    let limit = (metric.get_sum_distance(&Point { x: 500, y: 500 }, &coords) * 1.0002) as u64;
+    let region = metric.safe_region(&coords, limit);
+    assert!(region.count() > 100, "{}", region.count());
+    for _ in 0..500 {
+        let point = Point {
+            x: 400 + random(200),
+            y: 400 + random(200),
+        };
+        let expected = metric.get_sum_distance(&point, &coords) < limit as f64;
+        assert_eq!(region.contains(&point), expected, "at {:?}", point);
+    }
+}
+
+#[test]
+fn test_manhattan() {
+    let coords = example_coords();
+    let infinite = Manhattan.infinite_regions(&coords);
+    assert_eq!(infinite, [0, 1, 2, 5].iter().copied().collect());
+    check_against_brute_force(&Manhattan, &coords);
+    check_against_defaults(&Manhattan, &coords);
+
+    let coords = crate::parse_coordinates("4, 0\n2, 0\n0, 0\n5, 4\n2, 0").unwrap();
+    check_against_defaults(&Manhattan, &coords);
+}
+
+#[test]
+fn test_chebyshev() {
+    let coords = example_coords();
+    check_against_brute_force(&Chebyshev, &coords);
+
+    // The coordinate in the middle of the bottom edge owns an infinite strip under the Manhattan
+    // distance, but gets squeezed out by its neighbours under the Chebyshev distance.
+    let coords = crate::parse_coordinates("4, 0\n2, 0\n0, 0\n5, 4").unwrap();
+    assert_eq!(
+        Manhattan.infinite_regions(&coords),
+        [0, 1, 2, 3].iter().copied().collect()
+    );
+    assert_eq!(
+        Chebyshev.infinite_regions(&coords),
+        [0, 2, 3].iter().copied().collect()
+    );
+    check_against_brute_force(&Chebyshev, &coords);
+    check_against_defaults(&Chebyshev, &coords);
+    check_against_defaults(&Chebyshev, &example_coords());
+}
+
+#[test]
+fn test_euclidean() {
+    let coords = example_coords();
+    let infinite = Euclidean.infinite_regions(&coords);
+    assert_eq!(infinite, [0, 1, 2, 5].iter().copied().collect());
+    check_against_brute_force(&Euclidean, &coords);
+
+    // A coordinate in the middle of a hull edge owns an infinite strip, while duplicated
+    // coordinates never own anything.
+    let coords = crate::parse_coordinates("0, 0\n2, 0\n4, 0\n2, 4\n2, 2\n4, 0").unwrap();
+    let infinite = Euclidean.infinite_regions(&coords);
+    assert_eq!(infinite, [0, 1, 3].iter().copied().collect());
+    check_against_brute_force(&Euclidean, &coords);
+    check_against_defaults(&Euclidean, &coords);
+    check_against_defaults(&Euclidean, &example_coords());
+}
+
+#[test]
+#[ignore = "slow in debug builds, run with: cargo test --release -- --ignored"]
+fn test_scale() {
+    check_at_scale(&Manhattan);
+    check_at_scale(&Chebyshev);
+    check_at_scale(&Euclidean);
+}
+
+#[test]
+fn test_convex_hull() {
+    let coords = crate::parse_coordinates("0, 0\n2, 0\n4, 0\n2, 4\n2, 2").unwrap();
+    let hull = get_convex_hull(&coords);
+    assert_eq!(
+        hull,
+        vec![
+            (Point { x: 0, y: 0 }, Point { x: 4, y: 0 }),
+            (Point { x: 4, y: 0 }, Point { x: 2, y: 4 }),
+            (Point { x: 2, y: 4 }, Point { x: 0, y: 0 }),
+        ]
+    );
+}
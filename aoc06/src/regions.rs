@@ -0,0 +1,306 @@
+use crate::{Coordinate, CoordinateId, Point};
+use std::collections::{HashMap, VecDeque};
+use std::ops::RangeInclusive;
+
+// The closest coordinate of every location within a box, or None where coordinates are tied.
+
+pub struct RegionMap {
+    upper_left: Point,
+    width: usize,
+    height: usize,
+    owners: Vec<Option<CoordinateId>>,
+}
+
+impl RegionMap {
+    // Labels every location of the box by calling `closest` on it.
+
+    pub fn from_fn(
+        (upper_left, lower_right): (Point, Point),
+        closest: impl Fn(&Point) -> Option<CoordinateId>,
+    ) -> Self {
+        let (width, height) = get_size(&upper_left, &lower_right);
+        let owners = (0..height as i64)
+            .flat_map(|dy| {
+                let upper_left = &upper_left;
+                let closest = &closest;
+                (0..width as i64).map(move |dx| {
+                    closest(&Point {
+                        x: upper_left.x + dx,
+                        y: upper_left.y + dy,
+                    })
+                })
+            })
+            .collect();
+        RegionMap {
+            upper_left,
+            width,
+            height,
+            owners,
+        }
+    }
+
+    // Labels every location of the box with a breadth-first search starting from all of the
+    // coordinates at once, where `steps` are the moves between neighbouring locations. This only
+    // works for metrics where the distance between two locations is the fewest number of steps
+    // between them, along a path that stays within the box.
+    //
+    // The closest coordinates of a location are those of its neighbours one step closer to the
+    // coordinates, so a location reached from two different regions in the same number of steps
+    // is tied, and passes the tie on to the locations it reaches.
+
+    pub fn flood_fill(
+        coords: &[Coordinate],
+        (upper_left, lower_right): (Point, Point),
+        steps: &[(i64, i64)],
+    ) -> Self {
+        let (width, height) = get_size(&upper_left, &lower_right);
+        let mut map = RegionMap {
+            upper_left,
+            width,
+            height,
+            owners: vec![None; width * height],
+        };
+        let mut distances = vec![u32::MAX; width * height];
+        let mut queue = VecDeque::new();
+        for coord in coords {
+            let i = map
+                .get_index(&coord.point)
+                .expect("coordinates must be within the box");
+            if distances[i] == 0 {
+                // Coordinates sharing the same point are always tied.
+                map.owners[i] = None;
+            } else {
+                distances[i] = 0;
+                map.owners[i] = Some(coord.id);
+                queue.push_back(i);
+            }
+        }
+
+        while let Some(i) = queue.pop_front() {
+            let point = map.get_point(i);
+            for (dx, dy) in steps {
+                let neighbour = Point {
+                    x: point.x + dx,
+                    y: point.y + dy,
+                };
+                let j = match map.get_index(&neighbour) {
+                    Some(j) => j,
+                    None => continue,
+                };
+                if distances[j] == u32::MAX {
+                    distances[j] = distances[i] + 1;
+                    map.owners[j] = map.owners[i];
+                    queue.push_back(j);
+                } else if distances[j] == distances[i] + 1 && map.owners[j] != map.owners[i] {
+                    map.owners[j] = None;
+                }
+            }
+        }
+        map
+    }
+
+    pub fn get(&self, point: &Point) -> Option<CoordinateId> {
+        self.get_index(point).and_then(|i| self.owners[i])
+    }
+
+    // Returns the number of locations owned by each coordinate.
+
+    pub fn areas(&self) -> HashMap<CoordinateId, u64> {
+        self.owners
+            .iter()
+            .flatten()
+            .fold(HashMap::new(), |mut map, &id| {
+                *map.entry(id).or_default() += 1;
+                map
+            })
+    }
+
+    fn get_index(&self, point: &Point) -> Option<usize> {
+        let dx = point.x - self.upper_left.x;
+        let dy = point.y - self.upper_left.y;
+        if dx < 0 || dy < 0 || dx >= self.width as i64 || dy >= self.height as i64 {
+            return None;
+        }
+        Some(dy as usize * self.width + dx as usize)
+    }
+
+    fn get_point(&self, i: usize) -> Point {
+        Point {
+            x: self.upper_left.x + (i % self.width) as i64,
+            y: self.upper_left.y + (i / self.width) as i64,
+        }
+    }
+}
+
+fn get_size(upper_left: &Point, lower_right: &Point) -> (usize, usize) {
+    (
+        (lower_right.x - upper_left.x + 1).max(0) as usize,
+        (lower_right.y - upper_left.y + 1).max(0) as usize,
+    )
+}
+
+// Returns the sum of the distances from each value within the range to all of the given values,
+// using prefix sums over the sorted values, so that each sum takes O(log n).
+
+pub fn get_sum_distances(values: &[i64], min: i64, max: i64) -> Vec<i64> {
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable();
+    let mut prefix_sums = vec![0];
+    for value in &sorted {
+        prefix_sums.push(prefix_sums.last().unwrap() + value);
+    }
+    let n = sorted.len() as i64;
+    let total = *prefix_sums.last().unwrap();
+    (min..=max)
+        .map(|x| {
+            // The number of values below x, which are all `x - value` away.
+            let below = sorted.partition_point(|&value| value < x);
+            let below_sum = prefix_sums[below];
+            let below = below as i64;
+            (x * below - below_sum) + (total - below_sum - x * (n - below))
+        })
+        .collect()
+}
+
+// The locations whose total distance to all of the coordinates is below a limit.
+//
+// Under each of our metrics, the total distance is a sum of convex functions, so it is convex, and
+// so is the region. Each column of the region is then a single run of locations, which can be
+// found with a few evaluations of the total distance, rather than one per location.
+
+pub struct SafeRegion {
+    // The x of each column with any locations in it, and the range of their y, ordered by x.
+    columns: Vec<(i64, RangeInclusive<i64>)>,
+}
+
+impl SafeRegion {
+    // Finds the region within the bounds, using `total_distance` to get the total distance of a
+    // location within the bounds.
+    //
+    // A location's total distance is at least its total x distance, under any of our metrics, so
+    // columns whose total x distance is already too large are skipped without evaluating anything.
+
+    pub fn find(
+        coords: &[Coordinate],
+        (upper_left, lower_right): (Point, Point),
+        limit: u64,
+        total_distance: impl Fn(&Point) -> f64,
+    ) -> Self {
+        let limit = limit as f64;
+        let xs: Vec<i64> = coords.iter().map(|coord| coord.point.x).collect();
+        let x_sums = get_sum_distances(&xs, upper_left.x, lower_right.x);
+        let mut columns = vec![];
+        for (x, x_sum) in (upper_left.x..=lower_right.x).zip(x_sums) {
+            if x_sum as f64 >= limit {
+                continue;
+            }
+            let f = |y: i64| total_distance(&Point { x, y });
+            let y = find_minimum(upper_left.y, lower_right.y, f);
+            if f(y) >= limit {
+                continue;
+            }
+            // The total distance decreases up to the minimum, and increases after it.
+            let top = upper_left.y + partition_point(upper_left.y, y, |y| f(y) >= limit);
+            let bottom = y + partition_point(y, lower_right.y, |y| f(y) < limit) - 1;
+            columns.push((x, top..=bottom));
+        }
+        SafeRegion { columns }
+    }
+
+    pub fn contains(&self, point: &Point) -> bool {
+        match self.columns.binary_search_by_key(&point.x, |(x, _)| *x) {
+            Ok(i) => self.columns[i].1.contains(&point.y),
+            Err(_) => false,
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.columns
+            .iter()
+            .map(|(_, ys)| (ys.end() - ys.start() + 1) as u64)
+            .sum()
+    }
+}
+
+// Returns a value within min..=max where the convex function `f` is smallest, with a ternary
+// search. Convex functions only have flat stretches at their minimum, so whenever `f` is the same
+// at both probes, the minimum is between them.
+
+fn find_minimum(mut min: i64, mut max: i64, f: impl Fn(i64) -> f64) -> i64 {
+    while max - min > 2 {
+        let third = (max - min) / 3;
+        let (a, b) = (min + third, max - third);
+        let (fa, fb) = (f(a), f(b));
+        if fa < fb {
+            max = b - 1;
+        } else if fa > fb {
+            min = a + 1;
+        } else {
+            min = a;
+            max = b;
+        }
+    }
+    (min..=max)
+        .min_by(|&a, &b| f(a).total_cmp(&f(b)))
+        .unwrap_or(min)
+}
+
+// Returns how many values from min..=max, in order, satisfy `pred`, given that once `pred` fails
+// it fails for every later value.
+
+fn partition_point(min: i64, max: i64, pred: impl Fn(i64) -> bool) -> i64 {
+    let (mut lo, mut hi) = (min, max + 1);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if pred(mid) {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    lo - min
+}
+
+#[test]
+fn test_get_sum_distances() {
+    let values = [1, 1, 8, 3, 5, 8];
+    let sums = get_sum_distances(&values, -1, 10);
+    let expected: Vec<i64> = (-1..=10)
+        .map(|x: i64| values.iter().map(|value| (x - value).abs()).sum())
+        .collect();
+    assert_eq!(sums, expected);
+}
+
+#[test]
+fn test_safe_region() {
+    let coords = crate::parse_coordinates("1, 1\n1, 6\n8, 3\n3, 4\n5, 5\n8, 9").unwrap();
+    let total_distance = |point: &Point| -> f64 {
+        coords
+            .iter()
+            .map(|coord| ((point.x - coord.point.x).abs() + (point.y - coord.point.y).abs()) as f64)
+            .sum()
+    };
+    let bounds = (Point { x: -10, y: -10 }, Point { x: 20, y: 20 });
+    let region = SafeRegion::find(&coords, bounds.clone(), 32, total_distance);
+    assert_eq!(region.count(), 16);
+    for x in bounds.0.x..=bounds.1.x {
+        for y in bounds.0.y..=bounds.1.y {
+            let point = Point { x, y };
+            assert_eq!(region.contains(&point), total_distance(&point) < 32.0);
+        }
+    }
+    let region = SafeRegion::find(&coords, bounds, 0, total_distance);
+    assert_eq!(region.count(), 0);
+}
+
+#[test]
+fn test_find_minimum() {
+    assert_eq!(find_minimum(-10, 10, |x| ((x - 3) * (x - 3)) as f64), 3);
+    assert_eq!(find_minimum(0, 10, |x| x as f64), 0);
+    assert_eq!(find_minimum(0, 10, |x| -x as f64), 10);
+    // A flat minimum, which can be found anywhere along it.
+    let x = find_minimum(-100, 100, |x| (x.abs() - 20).max(0) as f64);
+    assert!((-20..=20).contains(&x));
+    assert_eq!(partition_point(0, 9, |x| x < 4), 4);
+    assert_eq!(partition_point(0, 9, |_| true), 10);
+}
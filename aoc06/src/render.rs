@@ -0,0 +1,166 @@
+use crate::metric::Metric;
+use crate::{Coordinate, CoordinateId, Error, Point, Result};
+use std::collections::HashSet;
+use std::fmt::Write;
+
+// Renders the map of regions, for checking the infinite region detection by eye:
+//  - '*' marks a coordinate
+//  - each coordinate's label marks the locations of its region
+//  - '.' marks locations tied between several coordinates
+//  - '#' marks the safe region, if a limit is given, where the total distance is below the limit
+//
+// The map covers every finite region, plus `margin` locations on each side, and is followed by a
+// legend of each coordinate's label, position and area, which says which regions are infinite.
+// There can't be more coordinates than labels.
+
+pub fn render_map(
+    coords: &[Coordinate],
+    metric: &dyn Metric,
+    margin: i64,
+    safe_limit: Option<u64>,
+) -> Result<String> {
+    if coords.len() > LABELS.len() {
+        return Err(Error::from(format!(
+            "can't label {} coordinates, only {}",
+            coords.len(),
+            LABELS.len()
+        )));
+    }
+    let (upper_left, lower_right) = metric.finite_bounds(coords);
+    let bounds = (
+        Point {
+            x: upper_left.x - margin,
+            y: upper_left.y - margin,
+        },
+        Point {
+            x: lower_right.x + margin,
+            y: lower_right.y + margin,
+        },
+    );
+    let regions = metric.label_regions(coords, bounds.clone());
+    let safe_region = safe_limit.map(|limit| metric.safe_region(coords, limit));
+    let is_coord: HashSet<(i64, i64)> = coords
+        .iter()
+        .map(|coord| (coord.point.x, coord.point.y))
+        .collect();
+
+    let mut map = String::new();
+    for y in bounds.0.y..=bounds.1.y {
+        for x in bounds.0.x..=bounds.1.x {
+            let point = Point { x, y };
+            let is_safe = safe_region
+                .as_ref()
+                .is_some_and(|region| region.contains(&point));
+            let c = if is_coord.contains(&(x, y)) {
+                '*'
+            } else if is_safe {
+                '#'
+            } else {
+                match regions.get(&point) {
+                    Some(id) => get_label(id),
+                    None => '.',
+                }
+            };
+            map.push(c);
+        }
+        map.push('\n');
+    }
+
+    let infinite = metric.infinite_regions(coords);
+    let finite_areas = metric.finite_areas(coords);
+    for coord in coords {
+        let area = if infinite.contains(&coord.id) {
+            String::from("infinite")
+        } else {
+            finite_areas
+                .get(&coord.id)
+                .copied()
+                .unwrap_or(0)
+                .to_string()
+        };
+        writeln!(
+            map,
+            "{}: ({}, {}) area {}",
+            get_label(coord.id),
+            coord.point.x,
+            coord.point.y,
+            area
+        )
+        .unwrap();
+    }
+    Ok(map)
+}
+
+// ASCII labels that can't be mistaken for each other, or for the other marks on the map. Uppercase
+// letters and digits that look like a lowercase letter or another digit are left out.
+const LABELS: &[u8] = b"abcdefghijklmnopqrstuvwxyzADEFHJLMNRTY347@%&+=?<>~^";
+
+fn get_label(id: CoordinateId) -> char {
+    char::from(*LABELS.get(id).expect("too many coordinates to label"))
+}
+
+#[test]
+fn test_render_map() {
+    use crate::metric::Manhattan;
+    let coords = crate::parse_coordinates("1, 1\n1, 6\n8, 3\n3, 4\n5, 5\n8, 9").unwrap();
+    let map = render_map(&coords, &Manhattan, 1, None).unwrap();
+    let expected = "\
+aaaaa.cccc
+a*aaa.cccc
+aaaddecccc
+aadddecc*c
+..d*deeccc
+bb.de*eecc
+b*b.eeee..
+bbb.eeefff
+bbb.eeffff
+bbb.ffff*f
+bbb.ffffff
+a: (1, 1) area infinite
+b: (1, 6) area infinite
+c: (8, 3) area infinite
+d: (3, 4) area 9
+e: (5, 5) area 17
+f: (8, 9) area infinite
+";
+    assert_eq!(map, expected);
+
+    let map = render_map(&coords, &Manhattan, 0, Some(32)).unwrap();
+    let expected = "\
+*aaa.ccc
+aaddeccc
+ad###cc*
+.#*###cc
+b###*#ec
+*b###ee.
+bb.eeeff
+bb.eefff
+bb.ffff*
+";
+    assert!(map.starts_with(expected), "{}", map);
+}
+
+#[test]
+fn test_render_labels() {
+    assert_eq!(LABELS.len(), 51);
+    let labels: Vec<char> = (0..LABELS.len()).map(get_label).collect();
+    assert!(labels.iter().all(|c| c.is_ascii_graphic()));
+    // At most one label from each group of lookalikes, and none that clash with the map's marks.
+    let lookalikes = [
+        "0oOQ", "1lI|", "2zZ", "5sS$", "6bG", "8B", "9g", "9q", "cC", "kK", "pP", "uU", "vV", "wW",
+        "xX",
+    ];
+    for group in &lookalikes {
+        let used: Vec<&char> = labels.iter().filter(|c| group.contains(**c)).collect();
+        assert!(used.len() <= 1, "{:?}", used);
+    }
+    assert!(labels.iter().all(|c| !"*.#".contains(*c)));
+    let unique: HashSet<char> = labels.iter().copied().collect();
+    assert_eq!(unique.len(), labels.len());
+
+    use crate::metric::Manhattan;
+    let input: Vec<String> = (0..52).map(|i| format!("{}, {}", i % 10, i / 10)).collect();
+    let coords = crate::parse_coordinates(&input.join("\n")).unwrap();
+    assert!(render_map(&coords, &Manhattan, 0, None).is_err());
+    assert!(render_map(&coords[..51], &Manhattan, 0, None).is_ok());
+}
@@ -0,0 +1,113 @@
+use crate::schedule::Schedule;
+use crate::{Graph, GraphError, NodeId};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+// What to annotate each node of the exported graph with.
+
+#[derive(Default)]
+pub struct DotOptions<'a> {
+    // The topological rank of each node, where nodes without dependencies have rank 0, and other
+    // nodes are ranked one after their latest ranked dependency. Nodes of the same rank are laid
+    // out side by side.
+    pub ranks: bool,
+    // The start and finish time of each node, with the nodes and edges of the critical path
+    // highlighted.
+    pub schedule: Option<&'a Schedule>,
+}
+
+// Exports the dependency graph in the Graphviz DOT format, eg: for `dot -Tsvg`. Nodes and edges
+// are sorted, so that the same graph always gives the same output.
+
+pub fn to_dot(graph: &Graph, options: &DotOptions) -> Result<String, GraphError> {
+    let ranks = if options.ranks {
+        Some(get_ranks(graph)?)
+    } else {
+        None
+    };
+    let critical_path: &[NodeId] = options
+        .schedule
+        .map_or(&[], |schedule| &schedule.critical_path);
+
+    let mut nodes: Vec<&NodeId> = graph.nodes.iter().collect();
+    nodes.sort();
+
+    let mut dot = String::from("digraph steps {\n");
+    for node_id in &nodes {
+        let mut label = node_id.to_string();
+        if let Some(ranks) = &ranks {
+            write!(label, "\nrank {}", ranks[*node_id]).unwrap();
+        }
+        let job = options
+            .schedule
+            .and_then(|schedule| schedule.jobs.iter().find(|job| &job.node == *node_id));
+        if let Some(job) = job {
+            write!(label, "\n{}-{}s", job.start, job.finish).unwrap();
+        }
+        write!(dot, "    {} [label={}", quote(node_id), quote(&label)).unwrap();
+        if critical_path.contains(node_id) {
+            dot.push_str(", color=red, penwidth=2");
+        }
+        dot.push_str("];\n");
+    }
+
+    for from in &nodes {
+        let mut outgoing: Vec<&NodeId> = graph
+            .outgoing_list
+            .get(*from)
+            .into_iter()
+            .flatten()
+            .collect();
+        outgoing.sort();
+        for to in outgoing {
+            write!(dot, "    {} -> {}", quote(from), quote(to)).unwrap();
+            let is_critical = critical_path
+                .windows(2)
+                .any(|pair| &pair[0] == *from && &pair[1] == to);
+            if is_critical {
+                dot.push_str(" [color=red, penwidth=2]");
+            }
+            dot.push_str(";\n");
+        }
+    }
+
+    if let Some(ranks) = &ranks {
+        let mut by_rank: BTreeMap<usize, Vec<&NodeId>> = BTreeMap::new();
+        for node_id in &nodes {
+            by_rank.entry(ranks[*node_id]).or_default().push(node_id);
+        }
+        for nodes in by_rank.values() {
+            let nodes: Vec<String> = nodes.iter().map(|node_id| quote(node_id)).collect();
+            writeln!(dot, "    {{ rank=same; {}; }}", nodes.join("; ")).unwrap();
+        }
+    }
+    dot.push_str("}\n");
+    Ok(dot)
+}
+
+fn get_ranks(graph: &Graph) -> Result<HashMap<&NodeId, usize>, GraphError> {
+    let mut ranks = HashMap::new();
+    for node_id in graph.iter_topo_sort() {
+        let node_id = graph.nodes.get(&node_id?).unwrap();
+        let rank = graph
+            .incoming_list
+            .get(node_id)
+            .into_iter()
+            .flatten()
+            .map(|incoming| ranks[incoming] + 1)
+            .max()
+            .unwrap_or(0);
+        ranks.insert(node_id, rank);
+    }
+    Ok(ranks)
+}
+
+// Quotes the string as a DOT id, where newlines become "\n" line breaks.
+
+fn quote(s: &str) -> String {
+    let escaped = s
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
@@ -0,0 +1,95 @@
+use crate::{add_time, Error, NodeId, Result, Time};
+use regex::Regex;
+use std::collections::HashMap;
+
+// How long each step takes.
+//
+// Steps can be given an explicit duration, otherwise a step named with a single ascii uppercase
+// letter takes the base duration plus the letter's position in the alphabet, eg: 'A' takes
+// base + 1. Any other step must have an explicit duration.
+
+pub struct Durations {
+    base: Time,
+    table: HashMap<NodeId, Time>,
+}
+
+impl Durations {
+    pub fn new(base: Time) -> Self {
+        Durations {
+            base,
+            table: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, node_id: NodeId, duration: Time) {
+        self.table.insert(node_id, duration);
+    }
+
+    // Adds every "X = 30" line of the input, overriding any durations already given.
+
+    pub fn extend_from_str(&mut self, input: &str) -> Result<()> {
+        for line in input.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let (node_id, duration) = parse_duration(line)
+                .ok_or_else(|| Error::from(format!("invalid duration: {:?}", line)))??;
+            self.insert(node_id, duration);
+        }
+        Ok(())
+    }
+
+    // The steps given an explicit duration.
+    pub fn steps(&self) -> impl Iterator<Item = &NodeId> {
+        self.table.keys()
+    }
+
+    pub fn get(&self, node_id: &str) -> Result<Time> {
+        if let Some(&duration) = self.table.get(node_id) {
+            return Ok(duration);
+        }
+        match node_id.as_bytes() {
+            [letter] if letter.is_ascii_uppercase() => {
+                add_time(self.base, Time::from(letter - b'A' + 1))
+            }
+            _ => Err(Error::from(format!(
+                "missing duration for step: {}",
+                node_id
+            ))),
+        }
+    }
+}
+
+impl Default for Durations {
+    fn default() -> Self {
+        Durations::new(60)
+    }
+}
+
+// Parses a line like "X = 30", or returns None if the line isn't a duration at all.
+
+pub fn parse_duration(line: &str) -> Option<Result<(NodeId, Time)>> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"^(?P<node>\S+)\s*=\s*(?P<duration>\S+)$").unwrap();
+    }
+
+    let caps = RE.captures(line)?;
+    Some(
+        caps["duration"]
+            .parse()
+            .map(|duration| (caps["node"].to_string(), duration))
+            .map_err(|_| Error::from(format!("invalid duration: {:?}", line))),
+    )
+}
+
+#[test]
+fn test_durations() -> Result<()> {
+    let mut durations = Durations::default();
+    durations.extend_from_str("compile = 30\n\nZ=1\n")?;
+    assert_eq!(durations.get("A")?, 61);
+    assert_eq!(durations.get("Z")?, 1);
+    assert_eq!(durations.get("compile")?, 30);
+    assert!(durations.get("link").is_err());
+    assert!(durations.get("a").is_err());
+    assert!(Durations::new(Time::MAX).get("A").is_err());
+    assert!(durations.extend_from_str("compile = soon").is_err());
+    assert!(durations.extend_from_str("compile takes 30").is_err());
+    Ok(())
+}
@@ -1,12 +1,20 @@
 #[macro_use]
 extern crate lazy_static;
-use std::convert::TryFrom;
 use std::str::FromStr;
 
 use regex::Regex;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
 use std::io::{Read, Write};
 
+mod dot;
+mod durations;
+mod schedule;
+
+use dot::{to_dot, DotOptions};
+use durations::{parse_duration, Durations};
+use schedule::{find_critical_path, Job, Schedule};
+
 type Error = std::boxed::Box<dyn std::error::Error>;
 type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -14,25 +22,77 @@ fn main() -> Result<()> {
     let mut input = String::new();
     std::io::stdin().read_to_string(&mut input)?;
 
-    let graph = Graph::parse(&input)?;
+    let (graph, mut durations) = parse_input(&input)?;
+
+    // Durations can optionally be given in a side file as the first argument, which override any
+    // durations given in the input. Use "-" to skip it.
+    if let Some(path) = std::env::args().nth(1).filter(|path| path != "-") {
+        durations.extend_from_str(&std::fs::read_to_string(path)?)?;
+        check_durations(&graph, &durations)?;
+    }
 
     writeln!(
         std::io::stdout(),
         "topological sort: {}",
-        graph.iter_topo_sort().collect::<Result<String, String>>()?
+        join_nodes(
+            &graph
+                .iter_topo_sort()
+                .collect::<Result<Vec<NodeId>, GraphError>>()?
+        )
     )?;
 
-    let workers = WorkerPool::new(5);
-
+    let schedule = WorkerPool::new(5).run_simulation(&graph, &durations)?;
     writeln!(
         std::io::stdout(),
         "time to process: {}",
-        workers.run_simulation(&graph)
+        schedule.total_time
+    )?;
+    writeln!(
+        std::io::stdout(),
+        "critical path: {} ({} seconds)",
+        join_nodes(&schedule.critical_path),
+        schedule.critical_path_time
+    )?;
+    writeln!(
+        std::io::stdout(),
+        "idle time per worker: {:?}",
+        schedule.idle_times()
     )?;
+
+    // The annotated graph can optionally be saved in the DOT format to the second argument.
+    if let Some(path) = std::env::args().nth(2) {
+        let options = DotOptions {
+            ranks: true,
+            schedule: Some(&schedule),
+        };
+        std::fs::write(&path, to_dot(&graph, &options)?)?;
+        writeln!(std::io::stdout(), "saved graph to: {}", path)?;
+    }
+
+    let sweep = sweep_pool_sizes(&graph, &durations)?;
+    if let Some((num_workers, time)) = find_best_pool_size(&sweep) {
+        writeln!(
+            std::io::stdout(),
+            "fewest workers for the fastest time: {} ({} seconds)",
+            num_workers,
+            time
+        )?;
+    }
     Ok(())
 }
 
-type NodeId = char;
+// Joins the nodes together, with a separator between them unless they're all single characters,
+// eg: "CABDFE", or "compile, link, test"
+
+fn join_nodes(nodes: &[NodeId]) -> String {
+    if nodes.iter().all(|node_id| node_id.chars().count() == 1) {
+        nodes.concat()
+    } else {
+        nodes.join(", ")
+    }
+}
+
+type NodeId = String;
 
 struct Graph {
     // Adjacency lists:
@@ -43,34 +103,8 @@ struct Graph {
 }
 
 impl Graph {
-    // given a set of accessible nodes, returns a Vec of the next neighboring nodes
-    fn next_accessible_nodes(&self, accessible_nodes: &HashSet<NodeId>) -> HashSet<NodeId> {
-        self.nodes
-            .iter()
-            .filter_map(|&node_id| {
-                let has_deps = if let Some(incoming_nodes) = self.incoming_list.get(&node_id) {
-                    // All nodes pointing to this node have already been visited
-                    !incoming_nodes.is_subset(&accessible_nodes)
-                } else {
-                    // There are no nodes pointing to this node:
-                    false
-                };
-                if !has_deps && !accessible_nodes.contains(&node_id) {
-                    Some(node_id)
-                } else {
-                    None
-                }
-            })
-            .collect::<HashSet<NodeId>>()
-    }
-
-    fn parse(input: &str) -> Result<Self> {
-        let edges = input
-            .lines()
-            .map(|line| line.parse())
-            .collect::<Result<Vec<Edge>>>()?;
-
-        let (incoming_list, outgoing_list, nodes) = edges.iter().fold(
+    fn from_edges(edges: Vec<Edge>) -> Self {
+        let (incoming_list, outgoing_list, nodes) = edges.into_iter().fold(
             (
                 HashMap::<NodeId, HashSet<NodeId>>::new(), // incoming
                 HashMap::<NodeId, HashSet<NodeId>>::new(), // outgoing
@@ -78,54 +112,304 @@ impl Graph {
             ),
             |(mut incoming, mut outgoing, mut nodes), edge| {
                 let Edge(from, to) = edge;
-                incoming.entry(*to).or_default().insert(*from);
-                outgoing.entry(*from).or_default().insert(*to);
-                nodes.insert(*from);
-                nodes.insert(*to);
+                incoming.entry(to.clone()).or_default().insert(from.clone());
+                outgoing.entry(from.clone()).or_default().insert(to.clone());
+                nodes.insert(from);
+                nodes.insert(to);
                 (incoming, outgoing, nodes)
             },
         );
-        Ok(Graph {
+        Graph {
             incoming_list,
             outgoing_list,
             nodes,
-        })
+        }
     }
 
-    pub fn iter_topo_sort(&self) -> IterGraph {
+    pub fn iter_topo_sort(&self) -> IterGraph<'_> {
         IterGraph {
             visited: HashSet::new(),
-            graph: &self,
+            dependencies: Dependencies::new(self),
+            graph: self,
+            is_stuck: false,
+        }
+    }
+
+    // Given the nodes visited so far, where none of the remaining nodes are accessible, returns a
+    // cycle through each strongly connected component of the remaining nodes that has one.
+    //
+    // Every remaining node has an unvisited incoming node, otherwise it would be accessible, so
+    // every remaining node is either on one of those cycles or depends on one.
+
+    fn find_cycles(&self, visited: &HashSet<NodeId>) -> GraphError {
+        let remaining: BTreeSet<&NodeId> = self
+            .nodes
+            .iter()
+            .filter(|node_id| !visited.contains(*node_id))
+            .collect();
+        let mut cycles: Vec<Vec<NodeId>> = self
+            .strongly_connected_components(&remaining)
+            .iter()
+            .filter_map(|component| self.cycle_within(component))
+            .collect();
+        cycles.sort();
+
+        let on_cycle: HashSet<&NodeId> = cycles.iter().flatten().collect();
+        let unreachable = remaining
+            .into_iter()
+            .filter(|node_id| !on_cycle.contains(node_id))
+            .cloned()
+            .collect();
+        GraphError::Cycle {
+            cycles,
+            unreachable,
+        }
+    }
+
+    // Splits the given nodes into strongly connected components, using Kosaraju's algorithm: a
+    // depth first search along outgoing edges orders the nodes by when they finish, then a search
+    // along incoming edges from each node in reverse of that order finds its component.
+
+    fn strongly_connected_components(&self, nodes: &BTreeSet<&NodeId>) -> Vec<BTreeSet<NodeId>> {
+        let neighbours = |list: &HashMap<NodeId, HashSet<NodeId>>, node_id: &NodeId| {
+            let mut next: Vec<NodeId> = list
+                .get(node_id)
+                .into_iter()
+                .flatten()
+                .filter(|next| nodes.contains(next))
+                .cloned()
+                .collect();
+            next.sort_unstable_by(|a, b| b.cmp(a));
+            next
+        };
+
+        let mut finished = vec![];
+        let mut seen = HashSet::new();
+        for &node_id in nodes {
+            if !seen.insert(node_id.clone()) {
+                continue;
+            }
+            let mut stack = vec![(node_id.clone(), neighbours(&self.outgoing_list, node_id))];
+            while let Some((node_id, next)) = stack.last_mut() {
+                match next.pop() {
+                    Some(next) => {
+                        if seen.insert(next.clone()) {
+                            let after = neighbours(&self.outgoing_list, &next);
+                            stack.push((next, after));
+                        }
+                    }
+                    None => {
+                        finished.push(node_id.clone());
+                        stack.pop();
+                    }
+                }
+            }
+        }
+
+        let mut components = vec![];
+        let mut assigned = HashSet::new();
+        for node_id in finished.into_iter().rev() {
+            if !assigned.insert(node_id.clone()) {
+                continue;
+            }
+            let mut component = BTreeSet::new();
+            let mut stack = vec![node_id];
+            while let Some(node_id) = stack.pop() {
+                for previous in neighbours(&self.incoming_list, &node_id) {
+                    if assigned.insert(previous.clone()) {
+                        stack.push(previous);
+                    }
+                }
+                component.insert(node_id);
+            }
+            components.push(component);
+        }
+        components
+    }
+
+    // Returns a cycle within a strongly connected component, starting from its smallest node, or
+    // None if the component is a single node without an edge to itself.
+    //
+    // Every node of a component with a cycle has an incoming node within the component, so walking
+    // backwards along those must eventually revisit a node.
+
+    fn cycle_within(&self, component: &BTreeSet<NodeId>) -> Option<Vec<NodeId>> {
+        let mut node = component.iter().next()?.clone();
+        let previous_in_component = |node_id: &NodeId| {
+            self.incoming_list
+                .get(node_id)?
+                .iter()
+                .filter(|previous| component.contains(*previous))
+                .min()
+                .cloned()
+        };
+        let mut walk: Vec<NodeId> = vec![];
+        while !walk.contains(&node) {
+            let previous = previous_in_component(&node)?;
+            walk.push(std::mem::replace(&mut node, previous));
+        }
+
+        // We walked backwards, so reverse the loop, and start it from its smallest node.
+        let start = walk.iter().position(|node_id| node_id == &node).unwrap();
+        let mut cycle: Vec<NodeId> = walk[start..].iter().rev().cloned().collect();
+        let smallest = (0..cycle.len()).min_by_key(|&i| &cycle[i]).unwrap();
+        cycle.rotate_left(smallest);
+        Some(cycle)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum GraphError {
+    // A cycle through each strongly connected component left unscheduled, in order of their
+    // smallest nodes. Each cycle lists its nodes in order, where the last node leads back to the
+    // first one. Every other unscheduled node depends on a cycle, and is listed as unreachable.
+    Cycle {
+        cycles: Vec<Vec<NodeId>>,
+        unreachable: BTreeSet<NodeId>,
+    },
+}
+
+impl Display for GraphError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraphError::Cycle {
+                cycles,
+                unreachable,
+            } => {
+                let plural = if cycles.len() == 1 { "" } else { "s" };
+                write!(f, "cycle{} detected: ", plural)?;
+                for (i, cycle) in cycles.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    for node_id in cycle {
+                        write!(f, "{} -> ", node_id)?;
+                    }
+                    write!(f, "{}", cycle[0])?;
+                }
+                if !unreachable.is_empty() {
+                    let nodes: Vec<&str> = unreachable.iter().map(String::as_str).collect();
+                    write!(f, ", which blocks: {}", nodes.join(", "))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+// Keeps count of how many of each node's dependencies are still to be finished, so that nodes
+// are ready as soon as their last dependency is finished, without rescanning the graph.
+
+struct Dependencies<'a> {
+    graph: &'a Graph,
+    remaining: HashMap<&'a NodeId, usize>,
+    ready: BTreeSet<&'a NodeId>,
+}
+
+impl<'a> Dependencies<'a> {
+    fn new(graph: &'a Graph) -> Self {
+        let remaining: HashMap<&NodeId, usize> = graph
+            .nodes
+            .iter()
+            .map(|node_id| {
+                let deps = graph.incoming_list.get(node_id).map_or(0, HashSet::len);
+                (node_id, deps)
+            })
+            .collect();
+        let ready = remaining
+            .iter()
+            .filter(|&(_, &deps)| deps == 0)
+            .map(|(&node_id, _)| node_id)
+            .collect();
+        Dependencies {
+            graph,
+            remaining,
+            ready,
+        }
+    }
+
+    // Takes the smallest of the nodes whose dependencies are all finished.
+    fn take_ready(&mut self) -> Option<&'a NodeId> {
+        self.ready.pop_first()
+    }
+
+    fn finish(&mut self, node_id: &NodeId) {
+        for next in self.graph.outgoing_list.get(node_id).into_iter().flatten() {
+            let deps = self.remaining.get_mut(next).unwrap();
+            *deps -= 1;
+            if *deps == 0 {
+                self.ready.insert(next);
+            }
         }
     }
 }
 
 struct IterGraph<'a> {
     visited: HashSet<NodeId>,
+    dependencies: Dependencies<'a>,
     graph: &'a Graph,
+    is_stuck: bool,
 }
 
 // Iterates over nodes in a topological sorted order
 impl<'a> Iterator for IterGraph<'a> {
     // Represents an ordered set of Nodes that have the same topological ordering
-    type Item = Result<NodeId, String>;
+    type Item = Result<NodeId, GraphError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.visited.len() == self.graph.nodes.len() {
+        if self.is_stuck || self.visited.len() == self.graph.nodes.len() {
             return None;
         }
-        let next_accessible = self.graph.next_accessible_nodes(&self.visited);
-        if let Some(&next) = next_accessible.iter().min() {
-            self.visited.insert(next);
-            Some(Ok(next))
+        if let Some(next) = self.dependencies.take_ready() {
+            self.dependencies.finish(next);
+            self.visited.insert(next.clone());
+            Some(Ok(next.clone()))
         } else {
-            return Some(Err(String::from(
-                "Unable to find next node to visit - possible cycle detected",
-            )));
+            self.is_stuck = true;
+            Some(Err(self.graph.find_cycles(&self.visited)))
         }
     }
 }
 
+// Parses the edges and durations of the input, where each line is either:
+//  - an edge, eg: "Step A must be finished before step B can begin." or "A -> B"
+//  - a duration, eg: "A = 30"
+
+fn parse_input(input: &str) -> Result<(Graph, Durations)> {
+    let mut edges = vec![];
+    let mut durations = Durations::default();
+    for line in input.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if let Some(duration) = parse_duration(line) {
+            let (node_id, duration) = duration?;
+            durations.insert(node_id, duration);
+        } else {
+            edges.push(line.parse()?);
+        }
+    }
+    let graph = Graph::from_edges(edges);
+    check_durations(&graph, &durations)?;
+    Ok((graph, durations))
+}
+
+// Returns an error if a duration is given for a step that isn't in the graph, which is most likely
+// a misspelt step name.
+
+fn check_durations(graph: &Graph, durations: &Durations) -> Result<()> {
+    match durations
+        .steps()
+        .filter(|node_id| !graph.nodes.contains(*node_id))
+        .min()
+    {
+        Some(node_id) => Err(Error::from(format!(
+            "duration given for unknown step: {}",
+            node_id
+        ))),
+        None => Ok(()),
+    }
+}
+
 struct Edge(NodeId, NodeId);
 
 impl FromStr for Edge {
@@ -133,33 +417,42 @@ impl FromStr for Edge {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         lazy_static! {
-            static ref RE: Regex = Regex::new(
-                r"Step (?P<from>[A-Z]) must be finished before step (?P<to>[A-Z]) can begin."
+            // eg: Step A must be finished before step B can begin
+            static ref SENTENCE: Regex = Regex::new(
+                r"^(?:[Ss]tep\s+)?(?P<from>\S+)\s+must\s+be\s+finished\s+before\s+(?:step\s+)?(?P<to>\S+)(?:\s+can\s+begin)?$"
             )
             .unwrap();
+            // eg: A -> B
+            static ref ARROW: Regex = Regex::new(r"^(?P<from>\S+?)\s*->\s*(?P<to>\S+)$").unwrap();
         }
 
-        let caps = RE.captures(s).unwrap();
-        // Note: we can shorten this like so:
-        // let from = char::try_from(caps["from"].chars().next()?)?;
-        // but only on nightly b/c NoneError is experimental: https://doc.rust-lang.org/std/option/struct.NoneError.html
-        let from = char::try_from(caps["from"].chars().next().unwrap())?;
-        let to = char::try_from(caps["to"].chars().next().unwrap())?;
-
-        if !from.is_ascii_uppercase() || !to.is_ascii_uppercase() {
-            return Err(Error::from(format!(
-                "Node should be ascii uppercase: {}, {}",
-                to, from
-            )));
-        }
-
-        Ok(Edge(from, to))
+        // Only a sentence ends with a dot, which isn't part of the last step's name, so a step
+        // named "B." ends one as "before step B..". Arrow edges keep every dot.
+        let sentence = s.strip_suffix('.').unwrap_or(s);
+        let caps = SENTENCE
+            .captures(sentence)
+            .or_else(|| ARROW.captures(s))
+            .ok_or_else(|| Error::from(format!("invalid edge: {:?}", s)))?;
+        Ok(Edge(caps["from"].to_string(), caps["to"].to_string()))
     }
 }
 
 type Time = u32;
 type WorkerId = usize;
 
+// Adds a duration to a time, or returns an error if the result is too late to represent.
+
+fn add_time(time: Time, duration: Time) -> Result<Time> {
+    time.checked_add(duration).ok_or_else(|| {
+        Error::from(format!(
+            "time overflowed: {} + {} seconds is more than {}",
+            time,
+            duration,
+            Time::MAX
+        ))
+    })
+}
+
 #[derive(PartialEq, Debug)]
 enum Status {
     Idle,
@@ -167,137 +460,138 @@ enum Status {
 }
 
 struct WorkerPool {
-    //    num_workers: u8,
     workers: Vec<Status>,
-    is_simple: bool,
     time: Time,
-    processed: HashSet<NodeId>,
-    in_progress: HashSet<NodeId>,
+    jobs: Vec<Job>,
 }
 
 impl WorkerPool {
-    fn new(n: u8) -> Self {
+    fn new(n: usize) -> Self {
         WorkerPool {
             workers: (0..n).map(|_| Status::Idle).collect::<Vec<Status>>(),
-            is_simple: false,
             time: 0,
-            processed: HashSet::<NodeId>::new(),
-            in_progress: HashSet::<NodeId>::new(),
+            jobs: vec![],
         }
     }
 
-    fn simple(mut self) -> Self {
-        self.is_simple = true;
-        self
-    }
-
-    // 'A' -> 61 (or 1 if simple)
-    // 'B' -> 62 (or 2 if simple)
-    // Ascii for 'A' is 65
+    // Frees the workers whose jobs are finished by now, and returns the nodes they finished.
 
-    fn get_node_duration(node: NodeId, is_simple: bool) -> Time {
-        let ascii_value = u32::from(node);
-        if is_simple {
-            ascii_value - 64
-        } else {
-            ascii_value - 4
+    fn finish_jobs(&mut self) -> Vec<NodeId> {
+        let mut finished = vec![];
+        for status in &mut self.workers {
+            if let Status::Busy { until, node } = status {
+                if *until <= self.time {
+                    finished.push(std::mem::take(node));
+                    *status = Status::Idle;
+                }
+            }
         }
+        finished
     }
 
-    // update our nodes that have finished processing
+    // Hands out the ready nodes in order to the idle workers, from the first worker on.
 
-    fn update_processed_nodes(&mut self) {
-        use Status::*;
-
-        let current_time = self.time;
-
-        // TODO: How to avoid "cannot move out of mutable reference" without having to move them
-        // here?
-
-        let mut processed = std::mem::replace(&mut self.processed, HashSet::new());
-        let mut in_progress = std::mem::replace(&mut self.in_progress, HashSet::new());
-        self.workers
-            .iter_mut()
-            .filter(|status| match status {
-                Idle => false,
-                Busy { until, .. } => until <= &current_time,
-            })
-            .for_each(|status| {
-                if let Busy {
-                    node: finished_node,
-                    ..
-                } = status
-                {
-                    processed.insert(*finished_node);
-                    in_progress.remove(finished_node);
-                    *status = Idle;
-                } else {
-                    panic!("invalid state - we should be filtering these out!")
-                }
+    fn start_jobs(
+        &mut self,
+        ready: &mut Dependencies,
+        durations: &HashMap<NodeId, Time>,
+    ) -> Result<()> {
+        for (worker_id, status) in self.workers.iter_mut().enumerate() {
+            if *status != Status::Idle {
+                continue;
+            }
+            let node_id = match ready.take_ready() {
+                Some(node_id) => node_id.clone(),
+                None => break,
+            };
+            let until = add_time(self.time, durations[&node_id])?;
+            self.jobs.push(Job {
+                worker: worker_id,
+                node: node_id.clone(),
+                start: self.time,
+                finish: until,
             });
-        self.processed = processed;
-        self.in_progress = in_progress;
+            *status = Status::Busy {
+                until,
+                node: node_id,
+            };
+        }
+        Ok(())
     }
 
-    // process the nodes until either they run out or all of the workers are busy.
-
-    // If all of the workers are busy, advance the time until the shortest job is finished and exit.
-
-    fn process_second(&mut self, mut nodes: Vec<NodeId>) {
-        use Status::*;
+    // Schedules the graph in topological order, while delegating to workers, and returns which
+    // worker ran each step and when.
+    //
+    // Time jumps straight to whenever the next job finishes, rather than ticking by each second.
 
-        // Update any new nodes that will now be processed
-        nodes.sort();
-
-        let mut in_progress = std::mem::replace(&mut self.in_progress, HashSet::new());
-        let updated_workers = self
-            .workers
+    fn run_simulation(mut self, graph: &Graph, durations: &Durations) -> Result<Schedule> {
+        let durations = graph
+            .nodes
             .iter()
-            .enumerate()
-            .filter(|&(_worker_id, status)| status == &Idle)
-            .zip(nodes.iter())
-            .map(|((worker_id, _status), &node_id)| {
-                in_progress.insert(node_id);
-                let job_length = WorkerPool::get_node_duration(node_id, self.is_simple);
-
-                (
-                    worker_id,
-                    Busy {
-                        until: self.time + job_length,
-                        node: node_id,
-                    },
-                )
-            })
-            .collect::<Vec<(WorkerId, Status)>>();
-        self.in_progress = in_progress;
-
-        updated_workers.into_iter().for_each(|(worker_id, status)| {
-            self.workers[worker_id] = status;
-        });
-    }
-
-    // Gets the time it takes to complete the graph in topological order, while delegating to
-    // workers
+            .map(|node_id| Ok((node_id.clone(), durations.get(node_id)?)))
+            .collect::<Result<HashMap<NodeId, Time>>>()?;
+        let (critical_path, critical_path_time) =
+            find_critical_path(graph, |node_id| durations[node_id])?;
 
-    fn run_simulation(mut self, graph: &Graph) -> u32 {
+        let mut dependencies = Dependencies::new(graph);
         self.time = 0;
         loop {
-            self.update_processed_nodes();
-            let nodes_ready_for_workers = graph
-                .next_accessible_nodes(&self.processed)
-                .into_iter()
-                // Omit nodes that are already in progress:
-                .filter(|node_id| !self.in_progress.contains(node_id))
-                .collect::<Vec<NodeId>>();
-
-            self.process_second(nodes_ready_for_workers);
-            if self.workers.iter().all(|status| status == &Status::Idle) {
-                break;
+            for node_id in self.finish_jobs() {
+                dependencies.finish(&node_id);
+            }
+            self.start_jobs(&mut dependencies, &durations)?;
+            let next_finish = self
+                .workers
+                .iter()
+                .filter_map(|status| match status {
+                    Status::Idle => None,
+                    Status::Busy { until, .. } => Some(*until),
+                })
+                .min();
+            match next_finish {
+                Some(until) => self.time = until,
+                None => break,
             }
-            self.time += 1;
         }
-        self.time
+        Ok(Schedule {
+            jobs: self.jobs,
+            num_workers: self.workers.len(),
+            total_time: self.time,
+            critical_path,
+            critical_path_time,
+        })
+    }
+}
+
+// Runs the graph through pools of 1 worker, 2 workers, etc, returning the time each pool size
+// takes. The sweep stops once a pool finishes in the time of the critical path, since extra
+// workers can't do any better than that.
+
+fn sweep_pool_sizes(graph: &Graph, durations: &Durations) -> Result<Vec<(usize, Time)>> {
+    let max_workers = graph.nodes.len().max(1);
+    let mut sweep = vec![];
+    for num_workers in 1..=max_workers {
+        let schedule = WorkerPool::new(num_workers).run_simulation(graph, durations)?;
+        sweep.push((num_workers, schedule.total_time));
+        if schedule.total_time <= schedule.critical_path_time {
+            break;
+        }
     }
+    Ok(sweep)
+}
+
+// Returns the smallest pool size that finishes in the fastest time.
+
+fn find_best_pool_size(sweep: &[(usize, Time)]) -> Option<(usize, Time)> {
+    sweep
+        .iter()
+        .min_by_key(|&&(num_workers, time)| (time, num_workers))
+        .copied()
+}
+
+#[cfg(test)]
+fn nodes(s: &str) -> Vec<NodeId> {
+    s.chars().map(String::from).collect()
 }
 
 #[test]
@@ -311,12 +605,12 @@ fn test_topo_sort() -> Result<()> {
         Step D must be finished before step E can begin.\n\
         Step F must be finished before step E can begin.\
     ";
-    let graph = Graph::parse(&s)?;
+    let (graph, _) = parse_input(s)?;
     assert_eq!(
         graph
             .iter_topo_sort()
-            .collect::<Result<Vec<NodeId>, String>>()?,
-        vec!('C', 'A', 'B', 'D', 'F', 'E')
+            .collect::<Result<Vec<NodeId>, GraphError>>()?,
+        nodes("CABDFE")
     );
     println!("test_topo_sort passed");
     Ok(())
@@ -333,13 +627,241 @@ fn test_completion_time() -> Result<()> {
         Step D must be finished before step E can begin.\n\
         Step F must be finished before step E can begin.\
     ";
-    let graph = Graph::parse(&s)?;
-    let mut workers = WorkerPool::new(2);
-    workers = workers.simple();
+    let (graph, _) = parse_input(s)?;
+    let workers = WorkerPool::new(2);
 
-    assert_eq!(workers.run_simulation(&graph), 15);
-    // Non-simple:
-    //    assert_eq!(workers.run_simulation(&graph), 258);
+    assert_eq!(
+        workers
+            .run_simulation(&graph, &Durations::new(0))?
+            .total_time,
+        15
+    );
     println!("test_completion_time passed");
     Ok(())
 }
+
+#[test]
+fn test_cycle_detection() -> Result<()> {
+    let s = "\
+        Step C must be finished before step A can begin.\n\
+        Step A must be finished before step F can begin.\n\
+        Step F must be finished before step C can begin.\n\
+        Step B must be finished before step C can begin.\n\
+        Step F must be finished before step E can begin.\n\
+        Step E must be finished before step D can begin.\n\
+        Step B must be finished before step G can begin.\
+    ";
+    let (graph, _) = parse_input(s)?;
+    let mut iter = graph.iter_topo_sort();
+    assert_eq!(iter.next(), Some(Ok(String::from("B"))));
+    assert_eq!(iter.next(), Some(Ok(String::from("G"))));
+    let err = iter.next().unwrap().unwrap_err();
+    assert_eq!(
+        err,
+        GraphError::Cycle {
+            cycles: vec![nodes("AFC")],
+            unreachable: nodes("DE").into_iter().collect(),
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "cycle detected: A -> F -> C -> A, which blocks: D, E"
+    );
+    assert_eq!(iter.next(), None);
+
+    let (graph, _) = parse_input("Step A must be finished before step A can begin.")?;
+    assert_eq!(
+        graph.iter_topo_sort().next(),
+        Some(Err(GraphError::Cycle {
+            cycles: vec![nodes("A")],
+            unreachable: BTreeSet::new(),
+        }))
+    );
+
+    // Two separate cycles, the second one downstream of the first, and a component whose
+    // nodes are not all on the reported cycle.
+    let s = "\
+        A -> B\n\
+        B -> A\n\
+        B -> C\n\
+        C -> D\n\
+        D -> E\n\
+        E -> D\n\
+        X -> Y\n\
+        Y -> X\n\
+        Y -> Z\n\
+        Z -> Y\
+    ";
+    let (graph, _) = parse_input(s)?;
+    let err = graph.iter_topo_sort().next().unwrap().unwrap_err();
+    assert_eq!(
+        err,
+        GraphError::Cycle {
+            cycles: vec![nodes("AB"), nodes("DE"), nodes("XY")],
+            unreachable: nodes("CZ").into_iter().collect(),
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "cycles detected: A -> B -> A; D -> E -> D; X -> Y -> X, which blocks: C, Z"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_schedule() -> Result<()> {
+    let s = "\
+        Step C must be finished before step A can begin.\n\
+        Step C must be finished before step F can begin.\n\
+        Step A must be finished before step B can begin.\n\
+        Step A must be finished before step D can begin.\n\
+        Step B must be finished before step E can begin.\n\
+        Step D must be finished before step E can begin.\n\
+        Step F must be finished before step E can begin.\
+    ";
+    let (graph, _) = parse_input(s)?;
+    let schedule = WorkerPool::new(2).run_simulation(&graph, &Durations::new(0))?;
+    let jobs: Vec<(WorkerId, &str, Time, Time)> = schedule
+        .jobs
+        .iter()
+        .map(|job| (job.worker, job.node.as_str(), job.start, job.finish))
+        .collect();
+    assert_eq!(
+        jobs,
+        vec![
+            (0, "C", 0, 3),
+            (0, "A", 3, 4),
+            (1, "F", 3, 9),
+            (0, "B", 4, 6),
+            (0, "D", 6, 10),
+            (0, "E", 10, 15),
+        ]
+    );
+    assert_eq!(schedule.idle_times(), vec![0, 9]);
+    assert_eq!(schedule.critical_path, nodes("CFE"));
+    assert_eq!(schedule.critical_path_time, 14);
+
+    let sweep = sweep_pool_sizes(&graph, &Durations::new(0))?;
+    assert_eq!(sweep, vec![(1, 21), (2, 15), (3, 14)]);
+    assert_eq!(find_best_pool_size(&sweep), Some((3, 14)));
+
+    // Steps that take days are scheduled as quickly as steps that take seconds.
+    let (graph, mut durations) = parse_input(s)?;
+    for (i, node_id) in nodes("ABCDEF").into_iter().enumerate() {
+        durations.insert(node_id, 86_400 * (i as Time + 1));
+    }
+    let schedule = WorkerPool::new(2).run_simulation(&graph, &durations)?;
+    assert_eq!(schedule.total_time, 86_400 * 15);
+    assert_eq!(schedule.critical_path_time, 86_400 * 14);
+
+    // Pools can have more workers than fit in a u8.
+    let edges: Vec<String> = (0..260).map(|i| format!("start -> step{}", i)).collect();
+    let (graph, mut durations) = parse_input(&edges.join("\n"))?;
+    durations.insert(String::from("start"), 1);
+    (0..260).for_each(|i| durations.insert(format!("step{}", i), 1));
+    let sweep = sweep_pool_sizes(&graph, &durations)?;
+    assert_eq!(sweep.len(), 260);
+    assert_eq!(find_best_pool_size(&sweep), Some((260, 2)));
+    Ok(())
+}
+
+#[test]
+fn test_parse_input() -> Result<()> {
+    let s = "\
+        fetch -> compile\n\
+        compile -> link\n\
+        Step fetch must be finished before step docs can begin.\n\
+        docs must be finished before link\n\
+        \n\
+        fetch = 5\n\
+        compile = 20\n\
+        link = 3\n\
+        docs = 10\
+    ";
+    let (graph, durations) = parse_input(s)?;
+    assert_eq!(
+        graph
+            .iter_topo_sort()
+            .collect::<Result<Vec<NodeId>, GraphError>>()?,
+        vec!["fetch", "compile", "docs", "link"]
+    );
+    let schedule = WorkerPool::new(2).run_simulation(&graph, &durations)?;
+    assert_eq!(schedule.total_time, 28);
+    assert_eq!(join_nodes(&schedule.critical_path), "fetch, compile, link");
+
+    let (graph, durations) = parse_input("fetch -> compile")?;
+    assert!(WorkerPool::new(2)
+        .run_simulation(&graph, &durations)
+        .is_err());
+    assert!(parse_input("fetch compile").is_err());
+    assert!(parse_input("fetch -> compile\nlink = 3").is_err());
+
+    // Only the dot ending a sentence is dropped from a step's name.
+    for (s, from, to) in [
+        ("Step A must be finished before step B can begin.", "A", "B"),
+        (
+            "Step A. must be finished before step B. can begin.",
+            "A.",
+            "B.",
+        ),
+        ("v1 must be finished before v2.", "v1", "v2"),
+        ("v1 must be finished before v2..", "v1", "v2."),
+        ("v1 must be finished before v2. can begin", "v1", "v2."),
+        ("v1. -> v2.", "v1.", "v2."),
+    ] {
+        let Edge(edge_from, edge_to) = s.parse()?;
+        assert_eq!((edge_from.as_str(), edge_to.as_str()), (from, to));
+    }
+    assert!(parse_input("fetch = later").is_err());
+
+    // Durations too long to add up are an error, rather than overflowing.
+    let (graph, durations) =
+        parse_input("fetch -> compile\nfetch = 3000000000\ncompile = 2000000000")?;
+    let err = WorkerPool::new(2)
+        .run_simulation(&graph, &durations)
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "time overflowed: 3000000000 + 2000000000 seconds is more than 4294967295"
+    );
+    let (graph, durations) =
+        parse_input("fetch -> compile\nfetch = 3000000000\ncompile = 1000000000")?;
+    let schedule = WorkerPool::new(2).run_simulation(&graph, &durations)?;
+    assert_eq!(schedule.total_time, 4_000_000_000);
+    Ok(())
+}
+
+#[test]
+fn test_to_dot() -> Result<()> {
+    let s = "\
+        Step C must be finished before step A can begin.\n\
+        Step C must be finished before step F can begin.\n\
+        Step A must be finished before step B can begin.\n\
+        Step A must be finished before step D can begin.\n\
+        Step B must be finished before step E can begin.\n\
+        Step D must be finished before step E can begin.\n\
+        Step F must be finished before step E can begin.\
+    ";
+    let (graph, _) = parse_input(s)?;
+    let dot = to_dot(&graph, &DotOptions::default())?;
+    assert!(dot.starts_with("digraph steps {\n    \"A\" [label=\"A\"];\n"));
+    assert!(dot.contains("    \"C\" -> \"A\";\n    \"C\" -> \"F\";\n"));
+    assert!(!dot.contains("rank"));
+
+    let schedule = WorkerPool::new(2).run_simulation(&graph, &Durations::new(0))?;
+    let options = DotOptions {
+        ranks: true,
+        schedule: Some(&schedule),
+    };
+    let dot = to_dot(&graph, &options)?;
+    assert!(dot.contains("    \"E\" [label=\"E\\nrank 3\\n10-15s\", color=red, penwidth=2];\n"));
+    assert!(dot.contains("    \"D\" [label=\"D\\nrank 2\\n6-10s\"];\n"));
+    assert!(dot.contains("    \"F\" -> \"E\" [color=red, penwidth=2];\n"));
+    assert!(dot.contains("    \"D\" -> \"E\";\n"));
+    assert!(dot.contains("    { rank=same; \"B\"; \"D\"; }\n"));
+
+    let (graph, _) = parse_input("A -> B\nB -> A")?;
+    assert!(to_dot(&graph, &DotOptions::default()).is_ok());
+    assert!(to_dot(&graph, &options).is_err());
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use crate::{add_time, Graph, NodeId, Result, Time, WorkerId};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+// A single step, run by a worker from its start time up until its finish time.
+
+#[derive(Debug, PartialEq)]
+pub struct Job {
+    pub worker: WorkerId,
+    pub node: NodeId,
+    pub start: Time,
+    pub finish: Time,
+}
+
+// The result of running a graph through a pool of workers.
+
+#[derive(Debug)]
+pub struct Schedule {
+    // Jobs in the order that they were started
+    pub jobs: Vec<Job>,
+    pub num_workers: usize,
+    pub total_time: Time,
+    // The chain of steps that takes the longest, no matter how many workers there are
+    pub critical_path: Vec<NodeId>,
+    pub critical_path_time: Time,
+}
+
+impl Schedule {
+    // Returns the time each worker spent without a job, up until the whole graph was finished.
+
+    pub fn idle_times(&self) -> Vec<Time> {
+        self.jobs.iter().fold(
+            vec![self.total_time; self.num_workers],
+            |mut idle_times, job| {
+                idle_times[job.worker] -= job.finish - job.start;
+                idle_times
+            },
+        )
+    }
+}
+
+// Returns the chain of dependent steps with the longest total duration, along with that duration.
+// This is the lower bound on the time to finish the graph, since each step of the chain can only
+// start once the previous one is finished. Ties are broken in favour of the smallest node ids.
+
+pub fn find_critical_path(
+    graph: &Graph,
+    duration: impl Fn(&NodeId) -> Time,
+) -> Result<(Vec<NodeId>, Time)> {
+    // The earliest finish time of each node, along with its latest finishing dependency.
+    let mut finish_times: HashMap<NodeId, (Time, Option<NodeId>)> = HashMap::new();
+    for node_id in graph.iter_topo_sort() {
+        let node_id = node_id?;
+        let previous = graph
+            .incoming_list
+            .get(&node_id)
+            .into_iter()
+            .flatten()
+            .max_by_key(|&incoming| (finish_times[incoming].0, Reverse(incoming)))
+            .cloned();
+        let start = previous
+            .as_ref()
+            .map_or(0, |previous| finish_times[previous].0);
+        let finish = add_time(start, duration(&node_id))?;
+        finish_times.insert(node_id, (finish, previous));
+    }
+
+    let mut path = vec![];
+    let last = finish_times
+        .iter()
+        .max_by_key(|&(node_id, (finish, _))| (*finish, Reverse(node_id)));
+    let total_time = last.map_or(0, |(_, (finish, _))| *finish);
+    let mut node = last.map(|(node_id, _)| node_id);
+    while let Some(node_id) = node {
+        path.push(node_id.clone());
+        node = finish_times[node_id].1.as_ref();
+    }
+    path.reverse();
+    Ok((path, total_time))
+}
@@ -0,0 +1,157 @@
+use crate::{Metadata, NodeId, Sum, Tree};
+
+// Nodes are stored in the order that their headers appear, so every child comes after its parent.
+// Visiting the nodes backwards is then a post-order traversal, where each node's children are
+// finished before the node itself, and visiting them forwards finishes each parent first.
+
+// The metadata sum and value of every node, indexed by NodeId.
+
+pub struct Evaluation {
+    // The sum of the metadata of each node and all of its descendants
+    pub metadata_sums: Vec<Sum>,
+    pub values: Vec<Sum>,
+}
+
+// A metadata entry of a node with children, which doesn't refer to any of them.
+
+#[derive(Debug, PartialEq)]
+pub struct DanglingEntry {
+    pub node: NodeId,
+    // The position of the entry within the node's metadata
+    pub index: usize,
+    pub entry: Metadata,
+}
+
+impl Tree {
+    // Computes the metadata sums and values of all the nodes in one pass.
+
+    pub fn evaluate(&self) -> Evaluation {
+        let mut metadata_sums = vec![0; self.nodes.len()];
+        let mut values = vec![0; self.nodes.len()];
+        for (id, node) in self.nodes.iter().enumerate().rev() {
+            let own_sum: Sum = node.metadata.iter().map(|&entry| Sum::from(entry)).sum();
+            metadata_sums[id] = own_sum
+                + node
+                    .children
+                    .iter()
+                    .map(|&child| metadata_sums[child])
+                    .sum::<Sum>();
+            values[id] = if node.children.is_empty() {
+                own_sum
+            } else {
+                node.metadata
+                    .iter()
+                    .filter_map(|&entry| self.get_child(id, entry))
+                    .map(|child| values[child])
+                    .sum()
+            };
+        }
+        Evaluation {
+            metadata_sums,
+            values,
+        }
+    }
+
+    // Returns the child that a metadata entry refers to, where 1 refers to the first child.
+
+    fn get_child(&self, id: NodeId, entry: Metadata) -> Option<NodeId> {
+        let index = (entry as usize).checked_sub(1)?;
+        self.nodes[id].children.get(index).copied()
+    }
+
+    pub fn parents(&self) -> Vec<Option<NodeId>> {
+        let mut parents = vec![None; self.nodes.len()];
+        for (id, node) in self.nodes.iter().enumerate() {
+            for &child in &node.children {
+                parents[child] = Some(id);
+            }
+        }
+        parents
+    }
+
+    // Returns the depth of each node, where the root has depth 0.
+
+    pub fn depths(&self) -> Vec<usize> {
+        let mut depths = vec![0; self.nodes.len()];
+        for (id, node) in self.nodes.iter().enumerate() {
+            for &child in &node.children {
+                depths[child] = depths[id] + 1;
+            }
+        }
+        depths
+    }
+
+    // Returns the number of nodes within each node's subtree, including the node itself.
+
+    pub fn subtree_sizes(&self) -> Vec<usize> {
+        let mut sizes = vec![1; self.nodes.len()];
+        for (id, node) in self.nodes.iter().enumerate().rev() {
+            sizes[id] += node
+                .children
+                .iter()
+                .map(|&child| sizes[child])
+                .sum::<usize>();
+        }
+        sizes
+    }
+
+    // Returns the nodes from the root down to the given node, or None if there's no such node.
+
+    pub fn path_to(&self, id: NodeId) -> Option<Vec<NodeId>> {
+        if id >= self.nodes.len() {
+            return None;
+        }
+        let parents = self.parents();
+        let mut path = vec![id];
+        while let Some(parent) = parents[*path.last().unwrap()] {
+            path.push(parent);
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    // Returns the metadata entries of nodes with children that don't refer to any child, and so
+    // add nothing to the node's value.
+
+    pub fn dangling_metadata(&self) -> Vec<DanglingEntry> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| !node.children.is_empty())
+            .flat_map(|(id, node)| {
+                node.metadata
+                    .iter()
+                    .enumerate()
+                    .filter(move |&(_, &entry)| self.get_child(id, entry).is_none())
+                    .map(move |(index, &entry)| DanglingEntry {
+                        node: id,
+                        index,
+                        entry,
+                    })
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_queries() -> crate::Result<()> {
+    let tree = Tree::parse("2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2")?;
+    let evaluation = tree.evaluate();
+    assert_eq!(evaluation.metadata_sums, vec![138, 33, 101, 99]);
+    assert_eq!(evaluation.values, vec![66, 33, 0, 99]);
+    assert_eq!(tree.parents(), vec![None, Some(0), Some(0), Some(2)]);
+    assert_eq!(tree.depths(), vec![0, 1, 1, 2]);
+    assert_eq!(tree.subtree_sizes(), vec![4, 1, 2, 1]);
+    assert_eq!(tree.path_to(3), Some(vec![0, 2, 3]));
+    assert_eq!(tree.path_to(0), Some(vec![0]));
+    assert_eq!(tree.path_to(4), None);
+    assert_eq!(
+        tree.dangling_metadata(),
+        vec![DanglingEntry {
+            node: 2,
+            index: 0,
+            entry: 2
+        }]
+    );
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use crate::{Metadata, Node, NodeId, Tree};
+
+// Builds a tree in code, by adding nodes under any node that has already been added, eg:
+//
+//     let mut builder = TreeBuilder::new(&[1, 1, 2]);
+//     builder.add_child(0, &[10, 11, 12]);
+//     let c = builder.add_child(0, &[2]);
+//     builder.add_child(c, &[99]);
+//     let tree = builder.build();
+//
+// Nodes can be added in any order, and are renumbered in the order of the license encoding when
+// the tree is built.
+
+pub struct TreeBuilder {
+    nodes: Vec<Node>,
+}
+
+impl TreeBuilder {
+    // Starts a tree with just the root, whose id is 0.
+
+    pub fn new(metadata: &[Metadata]) -> Self {
+        TreeBuilder {
+            nodes: vec![Node {
+                metadata: metadata.to_vec(),
+                children: vec![],
+            }],
+        }
+    }
+
+    // Adds a node as the last child of the parent, returning the id of the new node.
+
+    pub fn add_child(&mut self, parent: NodeId, metadata: &[Metadata]) -> NodeId {
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            metadata: metadata.to_vec(),
+            children: vec![],
+        });
+        self.nodes[parent].children.push(id);
+        id
+    }
+
+    pub fn build(self) -> Tree {
+        // Find the order of the nodes within the encoding, where each node is followed by the
+        // subtrees of its children.
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut stack = vec![0];
+        while let Some(id) = stack.pop() {
+            order.push(id);
+            stack.extend(self.nodes[id].children.iter().rev());
+        }
+
+        let mut new_ids = vec![0; self.nodes.len()];
+        for (new_id, &id) in order.iter().enumerate() {
+            new_ids[id] = new_id;
+        }
+        let mut nodes: Vec<Option<Node>> = self.nodes.into_iter().map(Some).collect();
+        let nodes = order
+            .iter()
+            .map(|&id| {
+                let node = nodes[id].take().unwrap();
+                Node {
+                    metadata: node.metadata,
+                    children: node.children.iter().map(|&child| new_ids[child]).collect(),
+                }
+            })
+            .collect();
+        Tree { nodes, root: 0 }
+    }
+}
+
+#[test]
+fn test_build_and_encode() -> crate::Result<()> {
+    // Add the nodes out of order, to check that they're renumbered.
+    let mut builder = TreeBuilder::new(&[1, 1, 2]);
+    let b = builder.add_child(0, &[10, 11, 12]);
+    let c = builder.add_child(0, &[2]);
+    builder.add_child(c, &[99]);
+    builder.add_child(b, &[]);
+    let tree = builder.build();
+    assert_eq!(tree.nodes[1].children, vec![2]);
+    assert_eq!(tree.nodes[3].children, vec![4]);
+    assert_eq!(
+        tree.to_license_string(),
+        "2 3 1 3 0 0 10 11 12 1 1 0 1 99 2 1 1 2"
+    );
+
+    let input = "2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2";
+    assert_eq!(Tree::parse(input)?.to_license_string(), input);
+    Ok(())
+}
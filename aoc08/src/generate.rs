@@ -0,0 +1,122 @@
+use crate::builder::TreeBuilder;
+#[cfg(test)]
+use crate::Sum;
+use crate::{Metadata, NodeId, Tree};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::ops::RangeInclusive;
+
+// Controls the shape of randomly generated trees.
+
+pub struct TreeShape {
+    // Nodes at this depth never have children, where the root has depth 0
+    pub max_depth: usize,
+    pub children: RangeInclusive<usize>,
+    pub metadata: RangeInclusive<usize>,
+    // No more nodes are added once the tree has this many
+    pub max_nodes: usize,
+}
+
+impl Default for TreeShape {
+    fn default() -> Self {
+        TreeShape {
+            max_depth: 5,
+            children: 0..=5,
+            metadata: 1..=3,
+            max_nodes: 2000,
+        }
+    }
+}
+
+// Generates a random tree of the given shape, where the same seed always gives the same tree.
+//
+// The metadata of a leaf is between 1 and 99, while the metadata of a node with children mostly
+// refers to its children, but sometimes refers to a child that doesn't exist.
+
+pub fn random_tree(shape: &TreeShape, seed: u64) -> Tree {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut num_nodes = 1;
+
+    // Decides how many children a new node will have, and generates its metadata to match.
+    let new_node = |rng: &mut StdRng, depth: usize, num_nodes: &mut usize| {
+        let num_children = if depth < shape.max_depth {
+            rng.gen_range(shape.children.clone())
+                .min(shape.max_nodes.saturating_sub(*num_nodes))
+        } else {
+            0
+        };
+        *num_nodes += num_children;
+        let max_entry = if num_children == 0 {
+            99
+        } else {
+            num_children + 1
+        };
+        let metadata: Vec<Metadata> = (0..rng.gen_range(shape.metadata.clone()))
+            .map(|_| rng.gen_range(1..=max_entry) as Metadata)
+            .collect();
+        (num_children, metadata)
+    };
+
+    let (num_children, metadata) = new_node(&mut rng, 0, &mut num_nodes);
+    let mut builder = TreeBuilder::new(&metadata);
+    // Nodes whose children are still to be added, along with their depth and number of children.
+    let mut stack: Vec<(NodeId, usize, usize)> = vec![(0, 0, num_children)];
+    while let Some((parent, depth, num_children)) = stack.pop() {
+        for _ in 0..num_children {
+            let (num_children, metadata) = new_node(&mut rng, depth + 1, &mut num_nodes);
+            let child = builder.add_child(parent, &metadata);
+            stack.push((child, depth + 1, num_children));
+        }
+    }
+    builder.build()
+}
+
+#[test]
+fn test_random_tree() -> crate::Result<()> {
+    let shape = TreeShape {
+        max_depth: 20,
+        children: 0..=4,
+        metadata: 1..=4,
+        max_nodes: 100_000,
+    };
+    let tree = random_tree(&shape, 8);
+    assert_eq!(
+        tree.to_license_string(),
+        random_tree(&shape, 8).to_license_string()
+    );
+    assert!(tree.nodes.len() <= shape.max_nodes);
+    assert!(tree
+        .depths()
+        .into_iter()
+        .all(|depth| depth <= shape.max_depth));
+
+    let parsed = Tree::parse(&tree.to_license_string())?;
+    assert_eq!(parsed.to_license_string(), tree.to_license_string());
+    let (evaluation, parsed_evaluation) = (tree.evaluate(), parsed.evaluate());
+    assert_eq!(evaluation.values, parsed_evaluation.values);
+    assert_eq!(evaluation.metadata_sums, parsed_evaluation.metadata_sums);
+    Ok(())
+}
+
+#[test]
+fn test_random_deep_tree() -> crate::Result<()> {
+    // A chain that's too deep to encode, parse or evaluate recursively on a test thread's stack,
+    // while still being quick to build in a debug build.
+    let depth = 100_000;
+    let shape = TreeShape {
+        max_depth: depth,
+        children: 1..=1,
+        metadata: 1..=1,
+        max_nodes: depth,
+    };
+    let tree = Tree::parse(&random_tree(&shape, 0).to_license_string())?;
+    assert_eq!(tree.nodes.len(), depth);
+    assert_eq!(tree.depths()[depth - 1], depth - 1);
+    let metadata_sum: Sum = tree
+        .nodes
+        .iter()
+        .map(|node| Sum::from(node.metadata[0]))
+        .sum();
+    assert_eq!(tree.sum_metadata(&tree.evaluate()), metadata_sum);
+    Ok(())
+}
@@ -1,110 +1,91 @@
-use std::collections::{HashMap, HashSet};
-use std::fmt::Display;
+use std::fmt::{Display, Formatter};
 use std::io::{Read, Write};
-use std::iter::FromIterator;
-use std::num::ParseIntError;
-use std::str::SplitAsciiWhitespace;
 
+#[cfg(test)]
+use std::collections::HashMap;
+#[cfg(test)]
 use std::{
     fs::{canonicalize, File},
     io::{prelude::*, BufReader},
     path::{Path, PathBuf},
 };
 
+mod analysis;
+mod builder;
+mod generate;
+mod parser;
+
+use analysis::Evaluation;
+use generate::{random_tree, TreeShape};
+use parser::parse_tree;
+
 type Error = std::boxed::Box<dyn std::error::Error>;
 type Result<T, E = Error> = std::result::Result<T, E>;
 
 fn main() -> Result<()> {
+    // With "generate" as the first argument, prints a random tree instead, seeded by the optional
+    // second argument.
+    if std::env::args().nth(1).as_deref() == Some("generate") {
+        let seed = std::env::args().nth(2).map_or(Ok(0), |seed| seed.parse())?;
+        let tree = random_tree(&TreeShape::default(), seed);
+        writeln!(std::io::stdout(), "{}", tree.to_license_string())?;
+        return Ok(());
+    }
+
     let mut input = String::new();
     std::io::stdin().read_to_string(&mut input)?;
 
     let tree = Tree::parse(&input)?;
+    let evaluation = tree.evaluate();
 
     // writeln!(std::io::stdout(), "tree: {}", tree,)?;
 
     writeln!(
         std::io::stdout(),
         "sum of metadata: {}",
-        tree.sum_metadata(),
+        tree.sum_metadata(&evaluation),
     )?;
 
     writeln!(
         std::io::stdout(),
         "tree root value: {}",
-        tree.get_root_value(),
+        tree.get_root_value(&evaluation),
+    )?;
+
+    let depths = tree.depths();
+    let sizes = tree.subtree_sizes();
+    writeln!(
+        std::io::stdout(),
+        "nodes: {}, dangling metadata entries: {}",
+        sizes[tree.root],
+        tree.dangling_metadata().len(),
     )?;
+    let deepest = (0..depths.len()).max_by_key(|&id| (depths[id], std::cmp::Reverse(id)));
+    if let Some(path) = deepest.and_then(|id| tree.path_to(id)) {
+        writeln!(
+            std::io::stdout(),
+            "deepest node: {} at depth {}, path: {:?}",
+            path[path.len() - 1],
+            path.len() - 1,
+            path,
+        )?;
+    }
 
     Ok(())
 }
 
-type NodeId = u32;
+// Nodes are stored in a flat arena, and are identified by their index within it.
+type NodeId = usize;
+type Metadata = u32;
 
 #[derive(Debug)]
 struct Node {
-    id: NodeId,
-    metadata: Vec<NodeId>, // 1 or more
-    children: Vec<NodeId>, // 0 or more
-}
-
-impl Node {
-    // runs through the iterator, parsing the nodes into Node structs. Returns the id of the root
-    // node, a HashMap of the Node structs, and what's left of the iterator.
-
-    // Is there a better option besides having to use an external iterator, transferring its
-    // ownership, and having to return it?
-
-    fn parse(
-        mut iter: SplitAsciiWhitespace,
-        mut id: NodeId,
-    ) -> Result<(
-        NodeId,
-        HashMap<NodeId, Self>,
-        SplitAsciiWhitespace,
-    )> {
-        if let (Some(children_str), Some(metadata_str)) = (iter.next(), iter.next()) {
-            let (num_children, num_metadata) =
-                (children_str.parse::<u32>()?, metadata_str.parse::<usize>()?);
-            let curr_node_id = id;
-            id += 1;
-            let mut nodes = HashMap::<NodeId, Node>::new();
-            let mut children = vec![];
-            // TODO: do this without a loop?
-            for _ in 0..num_children {
-                let (child_node_id, new_nodes, next_iter) = Node::parse(iter, id)?;
-                iter = next_iter; // re-assign the input for the next iteration
-                id += new_nodes.len() as u32;
-                children.push(child_node_id);
-
-                // Ideally, we'd use HashMap.extend, but we want to make sure we aren't overwriting anything here.
-                new_nodes.into_iter().map(|(node_id, node)| {
-                    if nodes.insert(node_id, node).is_some() {
-                        Err(Error::from(format!("collision when inserting node: {}", node_id)))
-                    } else {
-                        Ok(())
-                    }
-                }).collect::<Result<_>>()?;
-            }
-            let node = Node {
-                id: curr_node_id,
-                metadata: iter
-                    .by_ref()
-                    .take(num_metadata)
-                    .map(|metadata_string| metadata_string.parse::<u32>())
-                    .collect::<Result<Vec<u32>, ParseIntError>>()?,
-                children,
-            };
-            if let Some(node) = nodes.insert(node.id, node) {
-                return Err(Error::from(format!("overwriting node id: {}", node.id)));
-            }
-            Ok((curr_node_id, nodes, iter))
-        } else {
-            Err(Error::from(format!("Invalid iterator size")))
-        }
-    }
+    metadata: Vec<Metadata>, // 1 or more
+    children: Vec<NodeId>,   // 0 or more
 }
 
 struct Tree {
-    nodes: HashMap<NodeId, Node>,
+    nodes: Vec<Node>,
     root: NodeId,
 }
 
@@ -112,122 +93,74 @@ type Sum = u64;
 
 impl Tree {
     fn parse(input: &str) -> Result<Self> {
-        let (root, nodes, mut iter) = Node::parse(input.split_ascii_whitespace(), 0)?;
-        if let Some(_) = iter.next() {
-            return Err(Error::from("iter should be empty now."));
-        }
-        Ok(Tree { nodes, root })
+        Ok(parse_tree(input)?)
     }
 
-    // Part 1
-    fn sum_metadata(&self) -> u32 {
-        self.nodes
-            .values()
-            .flat_map(|node| node.metadata.clone())
-            .sum::<u32>()
+    // Part 1, given the evaluation of the tree
+    fn sum_metadata(&self, evaluation: &Evaluation) -> Sum {
+        evaluation.metadata_sums[self.root]
     }
 
-    // Part 2
-    fn get_root_value(&self) -> Sum {
-        let cache = HashMap::<NodeId, Sum>::new();
-        self._get_value(self.root, cache).0
+    // Part 2, given the evaluation of the tree
+    fn get_root_value(&self, evaluation: &Evaluation) -> Sum {
+        evaluation.values[self.root]
     }
 
-    // Return the value for a given NodeId
-    // While also maintaining a cache for the lookups...
-    fn _get_value(
-        &self,
-        id: NodeId,
-        mut cache: HashMap<NodeId, Sum>,
-    ) -> (Sum, HashMap<NodeId, Sum>) {
-        if let Some(&value) = cache.get(&id) {
-            return (value, cache);
-        }
-
-        let node = self
-            .nodes
-            .get(&id)
-            .expect(&format!("invalid node id: {}", id));
+    // Encodes the tree in the puzzle's format, where each node is written as its number of
+    // children and metadata entries, followed by its children, then its metadata entries.
 
-        let num_children = node.children.len() as u32;
-        let value = if num_children == 0 {
-            // get sum of node's metadata:
-            node.metadata.iter().map(|&id| u64::from(id)).sum()
-        } else {
-            // get value of the node's children:
-            // let mut temp_cache = std::mem::replace(&mut cache, HashMap::new());
-            let mut sum = 0;
-            // TODO: how to do this without a for loop? (see iterator below)
-            for &i in node.metadata.iter() {
-                if 1 <= i && i <= num_children {
-                    // recursive case
-                    let node_id: NodeId = node.children[(i - 1) as usize];
-                    let (node_value, new_cache_2) = self._get_value(node_id, cache);
-                    cache = new_cache_2;
-                    sum += node_value;
-                    // } else {
-                    //     // if i is out of range of the nodes children, then map it to 0
-                    //     0
-                }
-            }
-            // let v = node.metadata
-            //     .iter()
-            //     .map(move |&i| {
-            //         if 1 <= i && i <= num_children {
-            //             // recursive case
-            //             let node_id: NodeId = node.children[(i - 1) as usize];
-            //             let (node_value, new_cache_2) = self._get_value(node_id, temp_cache);
-            //             temp_cache = new_cache_2;
-            //             node_value
-            //         } else {
-            //             // if i is out of range of the nodes children, then map it to 0
-            //             0
-            //         }
-            //     })
-            //     .sum();
-            // std::mem::replace(&mut cache, temp_cache);
-            // cache = temp_cache;
-            sum
-        };
-        cache.insert(id, value);
-        (value, cache)
+    fn to_license_string(&self) -> String {
+        self.to_string()
     }
 }
 
 impl Display for Tree {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut sorted_nodes = self.nodes.values().collect::<Vec<&Node>>();
-        sorted_nodes.sort_by(|&node_1, &node_2| node_1.id.cmp(&node_2.id));
-
-        write!(
-            f,
-            "{}",
-            sorted_nodes
-                .iter()
-                .map(|n| format!("{:?}", n))
-                .collect::<Vec<String>>()
-                .join("\n")
-        )
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // Each node on the stack, along with how many of its children have been written so far.
+        let mut stack: Vec<(NodeId, usize)> = vec![(self.root, 0)];
+        let mut separator = "";
+        let mut write_number = |f: &mut Formatter<'_>, n: usize| {
+            let result = write!(f, "{}{}", separator, n);
+            separator = " ";
+            result
+        };
+        let root = &self.nodes[self.root];
+        write_number(f, root.children.len())?;
+        write_number(f, root.metadata.len())?;
+        while let Some((id, written)) = stack.last_mut() {
+            let node = &self.nodes[*id];
+            if let Some(&child) = node.children.get(*written) {
+                *written += 1;
+                let child_node = &self.nodes[child];
+                write_number(f, child_node.children.len())?;
+                write_number(f, child_node.metadata.len())?;
+                stack.push((child, 0));
+            } else {
+                for &entry in &node.metadata {
+                    write_number(f, entry as usize)?;
+                }
+                stack.pop();
+            }
+        }
+        Ok(())
     }
 }
 
 #[test]
 fn test_metadata_sum() -> Result<()> {
     let input = "2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2";
-    let tree = Tree::parse(&input)?;
-    assert_eq!(tree.sum_metadata(), 138);
-    assert_eq!(
-        tree.nodes.keys().collect::<HashSet<&NodeId>>(),
-        HashSet::<&NodeId>::from_iter(vec![0, 1, 2, 3].iter())
-    );
-    assert_eq!(tree.nodes.get(&0).unwrap().children, vec![1, 2]);
-    assert_eq!(tree.nodes.get(&1).unwrap().children, vec![]);
-    assert_eq!(tree.nodes.get(&2).unwrap().children, vec![3]);
-    assert_eq!(tree.nodes.get(&3).unwrap().children, vec![]);
+    let tree = Tree::parse(input)?;
+    assert_eq!(tree.sum_metadata(&tree.evaluate()), 138);
+    assert_eq!(tree.nodes.len(), 4);
+    assert_eq!(tree.nodes[0].children, vec![1, 2]);
+    assert_eq!(tree.nodes[1].children, vec![]);
+    assert_eq!(tree.nodes[2].children, vec![3]);
+    assert_eq!(tree.nodes[3].children, vec![]);
     println!("test_metadata_sum passed.");
     Ok(())
 }
 
+#[cfg(test)]
 fn lines_from_file(filename: impl AsRef<Path>) -> Vec<String> {
     let file = File::open(filename).expect("no such file");
     let buf = BufReader::new(file);
@@ -244,8 +177,8 @@ fn test_sample_input_metadata() -> Result<()> {
     let file_path = canonicalize(&file_name)?;
     println!("file_path: {:?}", file_path);
     let input = &lines_from_file(file_path)[0];
-    let tree = Tree::parse(&input)?;
-    assert_eq!(tree.sum_metadata(), 37905);
+    let tree = Tree::parse(input)?;
+    assert_eq!(tree.sum_metadata(&tree.evaluate()), 37905);
     println!("test_sample_metadata_sum passed.");
     Ok(())
 }
@@ -260,7 +193,7 @@ fn test_hashmap_extends() -> Result<()> {
 
     // Note: ideally, we should be able to know when something is being overwitten here...
 
-    map1.extend(map2.into_iter());
+    map1.extend(map2);
 
     assert_eq!(map1.get(&1), Some(&"5"));
     assert_eq!(map1.get(&2), Some(&"2"));
@@ -271,8 +204,8 @@ fn test_hashmap_extends() -> Result<()> {
 #[test]
 fn test_root_node_value() -> Result<()> {
     let input = "2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2";
-    let tree = Tree::parse(&input)?;
-    assert_eq!(tree.get_root_value(), 66);
+    let tree = Tree::parse(input)?;
+    assert_eq!(tree.get_root_value(&tree.evaluate()), 66);
     println!("test_root_node_value passed.");
     Ok(())
 }
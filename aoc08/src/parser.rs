@@ -0,0 +1,180 @@
+use crate::{Metadata, Node, NodeId, Tree};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    // The input ended while we were still expecting the given value
+    Truncated { offset: usize, expected: String },
+    InvalidNumber { offset: usize, token: String },
+    // There was more input after the root node was finished
+    TrailingInput { offset: usize },
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Truncated { offset, expected } => write!(
+                f,
+                "input ended at byte {} while expecting the {}",
+                offset, expected
+            ),
+            ParseError::InvalidNumber { offset, token } => {
+                write!(f, "invalid number at byte {}: {:?}", offset, token)
+            }
+            ParseError::TrailingInput { offset } => write!(
+                f,
+                "unexpected input at byte {} after the end of the tree",
+                offset
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Iterates over the whitespace separated tokens of the input, along with their byte offsets.
+
+struct Tokens<'a> {
+    input: &'a str,
+    offset: usize,
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = (usize, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.input[self.offset..];
+        let start = self.offset + rest.find(|c: char| !c.is_ascii_whitespace())?;
+        let len = self.input[start..]
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(self.input.len() - start);
+        self.offset = start + len;
+        Some((start, &self.input[start..start + len]))
+    }
+}
+
+impl<'a> Tokens<'a> {
+    fn next_number<T: std::str::FromStr>(
+        &mut self,
+        expected: impl FnOnce() -> String,
+    ) -> Result<T, ParseError> {
+        let (offset, token) = self.next().ok_or_else(|| ParseError::Truncated {
+            offset: self.input.len(),
+            expected: expected(),
+        })?;
+        token.parse().map_err(|_| ParseError::InvalidNumber {
+            offset,
+            token: token.to_string(),
+        })
+    }
+
+    // Reads the header of a new node, and adds it to the end of the nodes.
+
+    fn next_node(&mut self, nodes: &mut Vec<Node>) -> Result<Frame, ParseError> {
+        let id = nodes.len();
+        let num_children = self.next_number(|| format!("child count of node {}", id))?;
+        let num_metadata = self.next_number(|| format!("metadata count of node {}", id))?;
+        // The metadata isn't preallocated, as the count comes from the input, and may be far more
+        // than the input actually holds.
+        nodes.push(Node {
+            metadata: vec![],
+            children: vec![],
+        });
+        Ok(Frame {
+            id,
+            num_children,
+            num_metadata,
+        })
+    }
+}
+
+// A node whose children are still being parsed.
+
+struct Frame {
+    id: NodeId,
+    num_children: u32,
+    num_metadata: usize,
+}
+
+// Parses the tree with an explicit stack of the nodes being parsed, instead of recursing, so that
+// trees of any depth can be parsed. Nodes are stored in the order that their headers appear, so
+// the root is always node 0.
+
+pub fn parse_tree(input: &str) -> Result<Tree, ParseError> {
+    let mut tokens = Tokens { input, offset: 0 };
+    let mut nodes = vec![];
+    let mut stack = vec![tokens.next_node(&mut nodes)?];
+
+    while let Some(frame) = stack.last_mut() {
+        if frame.num_children > 0 {
+            frame.num_children -= 1;
+            let parent = frame.id;
+            let child = tokens.next_node(&mut nodes)?;
+            nodes[parent].children.push(child.id);
+            stack.push(child);
+        } else {
+            let frame = stack.pop().unwrap();
+            for i in 0..frame.num_metadata {
+                let entry: Metadata = tokens
+                    .next_number(|| format!("metadata entry {} of node {}", i + 1, frame.id))?;
+                nodes[frame.id].metadata.push(entry);
+            }
+        }
+    }
+
+    if let Some((offset, _)) = tokens.next() {
+        return Err(ParseError::TrailingInput { offset });
+    }
+    Ok(Tree { nodes, root: 0 })
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!(
+        parse_tree("2 3 0 3 10 11 12 1 1 0 1 99 2 1 1").err(),
+        Some(ParseError::Truncated {
+            offset: 33,
+            expected: String::from("metadata entry 3 of node 0"),
+        })
+    );
+    assert_eq!(
+        parse_tree("  ").err(),
+        Some(ParseError::Truncated {
+            offset: 2,
+            expected: String::from("child count of node 0"),
+        })
+    );
+    assert_eq!(
+        parse_tree("0 1 5\n 7").err(),
+        Some(ParseError::TrailingInput { offset: 7 })
+    );
+    assert_eq!(
+        parse_tree("1 1 0 x").err(),
+        Some(ParseError::InvalidNumber {
+            offset: 6,
+            token: String::from("x"),
+        })
+    );
+    // Metadata counts far beyond the end of the input.
+    for input in ["0 18446744073709551615", "0 4000000000"] {
+        assert_eq!(
+            parse_tree(input).err(),
+            Some(ParseError::Truncated {
+                offset: input.len(),
+                expected: String::from("metadata entry 1 of node 0"),
+            })
+        );
+    }
+}
+
+#[test]
+fn test_parse_deep_tree() -> Result<(), ParseError> {
+    // A chain of nodes, each with a single child, that would overflow the stack if parsed
+    // recursively.
+    let depth = 1_000_000;
+    let input = "1 1 ".repeat(depth - 1) + "0 1" + &" 1".repeat(depth);
+    let tree = parse_tree(&input)?;
+    assert_eq!(tree.nodes.len(), depth);
+    assert_eq!(tree.nodes[depth - 2].children, vec![depth - 1]);
+    Ok(())
+}
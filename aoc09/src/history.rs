@@ -0,0 +1,79 @@
+use crate::{MarbleId, Score};
+
+// A turn where a player scored, where players are numbered from 0.
+
+#[derive(Debug, PartialEq)]
+pub struct ScoringEvent {
+    pub turn: usize,
+    pub player: usize,
+    pub placed: MarbleId,
+    // None when the scoring marble was alone in the circle, and so nothing could be removed
+    pub removed: Option<MarbleId>,
+    pub points: Score,
+}
+
+// The final scores of a game, along with its scoring events if they were recorded.
+
+#[derive(Debug)]
+pub struct Scoreboard {
+    pub scores: Vec<Score>,
+    pub events: Vec<ScoringEvent>,
+    // The turn where the winner last moved into the lead on their own, and then kept it until the
+    // end, or None if the game ended in a tie or without any points.
+    pub winner_took_lead: Option<usize>,
+    leader: Option<usize>,
+    top_score: Score,
+    is_recording: bool,
+}
+
+impl Scoreboard {
+    pub fn new(players: usize, is_recording: bool) -> Self {
+        Scoreboard {
+            scores: vec![0; players],
+            events: vec![],
+            winner_took_lead: None,
+            leader: None,
+            top_score: 0,
+            is_recording,
+        }
+    }
+
+    pub fn add(&mut self, event: ScoringEvent) {
+        let score = &mut self.scores[event.player];
+        *score += event.points;
+
+        // Scores only ever go up, so the player is now either the only one with the top score, tied
+        // for it, or still behind.
+        if *score > self.top_score {
+            if self.leader != Some(event.player) {
+                self.leader = Some(event.player);
+                self.winner_took_lead = Some(event.turn);
+            }
+            self.top_score = *score;
+        } else if *score == self.top_score {
+            self.leader = None;
+            self.winner_took_lead = None;
+        }
+
+        if self.is_recording {
+            self.events.push(event);
+        }
+    }
+
+    // Returns the player with the highest score, numbered from 0 like the scores, and their score,
+    // favouring the earliest player when there's a tie.
+
+    pub fn winner(&self) -> (usize, Score) {
+        self.scores
+            .iter()
+            .copied()
+            .enumerate()
+            .fold((0, 0), |best, (player, score)| {
+                if score > best.1 {
+                    (player, score)
+                } else {
+                    best
+                }
+            })
+    }
+}
@@ -4,7 +4,12 @@ use std::io::{Read, Write};
 use std::str::FromStr;
 
 use regex::Regex;
-use std::collections::HashMap;
+
+mod history;
+mod rules;
+
+use history::{Scoreboard, ScoringEvent};
+use rules::GameRules;
 
 type Error = std::boxed::Box<dyn std::error::Error>;
 type Result<T, E = Error> = std::result::Result<T, E>;
@@ -15,12 +20,20 @@ fn main() -> Result<()> {
 
     let game = input.parse::<Game>()?;
 
-    let game_2 = Game::new(game.players.len(), game.marbles * 100);
+    let game_2 = Game::new(game.players, game.marbles * 100, game.rules);
+    let scoreboard = game.with_history().play();
+    let (winner, score) = scoreboard.winner();
+    writeln!(std::io::stdout(), "winning score: {}", score)?;
+    // Players are numbered from 0, the same as in the scoreboard.
     writeln!(
         std::io::stdout(),
-        "winning score: {}",
-        game.get_winning_score()
+        "winner: player {}, scoring events: {}",
+        winner,
+        scoreboard.events.len()
     )?;
+    if let Some(turn) = scoreboard.winner_took_lead {
+        writeln!(std::io::stdout(), "winner took the lead on turn: {}", turn)?;
+    }
 
     writeln!(
         std::io::stdout(),
@@ -31,31 +44,56 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-type Score = u32;
+type Score = u64;
 
 struct Game {
-    players: Vec<Score>,
+    players: usize,
     marbles: usize,
+    rules: GameRules,
     circle: Circle,
+    is_recording: bool,
 }
 
 impl Game {
-    fn new(players: usize, marbles: usize) -> Self {
+    fn new(players: usize, marbles: usize, rules: GameRules) -> Self {
         Game {
-            players: vec![0; players],
+            players,
             marbles,
-            circle: Circle::new(),
+            rules,
+            circle: Circle::with_capacity(marbles),
+            is_recording: false,
         }
     }
 
-    fn get_winning_score(mut self) -> u32 {
-        for i in 1..=self.marbles {
-            let points = self.circle.turn(i as u32);
-            let player_index = (i - 1) % self.players.len();
-            self.players[player_index] += points;
+    // Records every scoring event of the game in the scoreboard.
+
+    fn with_history(mut self) -> Self {
+        self.is_recording = true;
+        self
+    }
+
+    // Plays the game, where turn i places marble i.
+
+    fn play(mut self) -> Scoreboard {
+        let mut scoreboard = Scoreboard::new(self.players, self.is_recording);
+        for turn in 1..=self.marbles {
+            let placed = turn as MarbleId;
+            let (points, removed) = self.circle.turn(placed, &self.rules);
+            if points > 0 {
+                scoreboard.add(ScoringEvent {
+                    turn,
+                    player: (turn - 1) % self.players,
+                    placed,
+                    removed,
+                    points,
+                });
+            }
         }
-        // 8317
-        *self.players.iter().max().unwrap()
+        scoreboard
+    }
+
+    fn get_winning_score(self) -> Score {
+        self.play().winner().1
     }
 }
 
@@ -65,49 +103,65 @@ impl FromStr for Game {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         lazy_static! {
             static ref RE: Regex = Regex::new(
-                r"(?P<players>[0-9]+) players; last marble is worth (?P<marbles>[0-9]+) points"
+                r"(?x)
+                (?P<players>[0-9]+)\ players;\ last\ marble\ is\ worth\ (?P<marbles>[0-9]+)\ points
+                # optional overrides of the default rules, eg: ; modulus=19 removal=5
+                (?:;(?P<rules>.*))?
+                "
             )
             .unwrap();
         }
 
-        let caps = RE.captures(s).unwrap();
+        let caps = RE
+            .captures(s)
+            .ok_or_else(|| Error::from(format!("invalid game: {:?}", s.trim())))?;
         let players = usize::from(caps["players"].parse::<u16>()?);
         let marbles = caps["marbles"].parse()?;
-        Ok(Game::new(players, marbles))
+        let rules = match caps.name("rules") {
+            Some(rules) => rules.as_str().parse()?,
+            None => GameRules::default(),
+        };
+        Ok(Game::new(players, marbles, rules))
     }
 }
 
 type MarbleId = u32;
 
+// The circle is a doubly linked list, where the neighbours of each marble are stored at the index
+// of its id. Marbles are placed in order of their ids, so the lists only ever grow at the end, and
+// the slots of removed marbles are simply never visited again.
+
 struct Circle {
-    map: HashMap<MarbleId, Marble>,
+    next: Vec<MarbleId>,
+    prev: Vec<MarbleId>,
     current: MarbleId, // id of the current marble in the circle
-                       // current: MarbleId, // count
+    len: usize,
 }
 
 impl Circle {
-    // starts with a single marble
-
-    fn new() -> Self {
-        let mut map = HashMap::new();
-        map.insert(
-            0,
-            Marble {
-                id: 0,
-                next: 0,
-                prev: 0,
-            },
-        );
-        Circle { map, current: 0 }
+    // Starts with a single marble, with room for marbles up to the given id without reallocating.
+
+    fn with_capacity(marbles: usize) -> Self {
+        let mut next = Vec::with_capacity(marbles + 1);
+        let mut prev = Vec::with_capacity(marbles + 1);
+        next.push(0);
+        prev.push(0);
+        Circle {
+            next,
+            prev,
+            current: 0,
+            len: 1,
+        }
     }
 
     // Returns a vec representing the circle of marbles. For testing only.
 
+    #[cfg(test)]
     fn get_vec(&self) -> Vec<MarbleId> {
         let mut curr = self.current;
         let mut vec = vec![curr];
         loop {
-            curr = self.map.get(&curr).unwrap().next;
+            curr = self.next[curr as usize];
             if curr == self.current {
                 break;
             }
@@ -117,28 +171,36 @@ impl Circle {
     }
 
     // Takes a turn in the game, returning the score for that turn.
-    fn turn(&mut self, new: MarbleId) -> Score {
+    // Also returns the marble that was removed, if any.
+    // The last marble is never removed, so a scoring marble only scores itself when it's alone.
+    fn turn(&mut self, new: MarbleId, rules: &GameRules) -> (Score, Option<MarbleId>) {
         let new_id = new;
 
-        if new_id % 23 == 0 {
-            // Remove the marble that is 7 marbles counter clockwise of the current marble.
-            let remove_id = self.get_counter_clockwise(7);
-            let marble_to_remove = self.map.get_mut(&remove_id).unwrap();
-            self.current = marble_to_remove.next;
-            self.remove_marble(&remove_id);
-            new_id + remove_id
+        if new_id.is_multiple_of(rules.scoring_modulus) {
+            if self.len == 1 {
+                return (Score::from(new_id), None);
+            }
+            // Remove the marble that is `removal_offset` marbles counter clockwise of the current
+            // marble, skipping whole laps around the circle.
+            let remove_id = self.get_counter_clockwise(rules.removal_offset % self.len);
+            self.current = self.next[remove_id as usize];
+            self.remove_marble(remove_id);
+            (
+                Score::from(new_id) + Score::from(remove_id),
+                Some(remove_id),
+            )
         } else {
-            let prev_id = self.get_clockwise(1);
+            let prev_id = self.get_clockwise(rules.insertion_offset % self.len);
             self.insert_marble_after(prev_id, new_id);
             self.current = new_id;
-            0
+            (0, None)
         }
     }
 
     fn get_counter_clockwise(&self, n: usize) -> MarbleId {
         let mut curr_id = self.current;
         for _ in 0..n {
-            curr_id = self.map.get(&curr_id).unwrap().prev;
+            curr_id = self.prev[curr_id as usize];
         }
         curr_id
     }
@@ -146,64 +208,47 @@ impl Circle {
     fn get_clockwise(&self, n: usize) -> MarbleId {
         let mut curr_id = self.current;
         for _ in 0..n {
-            curr_id = self.map.get(&curr_id).unwrap().next;
+            curr_id = self.next[curr_id as usize];
         }
         curr_id
     }
 
-    fn insert_marble_after(&mut self, after: MarbleId, new_id: MarbleId) {
-        let prev = self.map.get_mut(&after).unwrap();
+    // Marbles must be inserted in order of their ids.
 
-        let next_id = prev.next;
-        prev.next = new_id;
-        let next = self.map.get_mut(&next_id).unwrap();
-        next.prev = new_id;
-
-        self.map.insert(
-            new_id,
-            Marble {
-                id: new_id,
-                prev: after,
-                next: next_id,
-            },
-        );
+    fn insert_marble_after(&mut self, after: MarbleId, new_id: MarbleId) {
+        let next_id = self.next[after as usize];
+        self.next.resize(new_id as usize + 1, 0);
+        self.prev.resize(new_id as usize + 1, 0);
+
+        self.next[new_id as usize] = next_id;
+        self.prev[new_id as usize] = after;
+        self.next[after as usize] = new_id;
+        self.prev[next_id as usize] = new_id;
+        self.len += 1;
     }
 
-    fn remove_marble(&mut self, id: &MarbleId) {
-        let marble_to_remove = self.map.get(&id).unwrap();
-        let [prev_id, next_id] = [marble_to_remove.prev, marble_to_remove.next];
-
-        // remove the marble:
-        self.map.remove(&id);
+    fn remove_marble(&mut self, id: MarbleId) {
+        let [prev_id, next_id] = [self.prev[id as usize], self.next[id as usize]];
 
         // update the prev/next marbles to excise the references:
-        let prev = self.map.get_mut(&prev_id).unwrap();
-        prev.next = next_id;
-        let next = self.map.get_mut(&next_id).unwrap();
-        next.prev = prev_id;
+        self.next[prev_id as usize] = next_id;
+        self.prev[next_id as usize] = prev_id;
+        self.len -= 1;
     }
 }
 
-// Alternatively, we could use a linked list. But this Marble node should be fine for our purposes
-
-struct Marble {
-    id: MarbleId, // This is also the point value of the marble
-    next: MarbleId,
-    prev: MarbleId,
-}
-
 #[test]
 fn test_circle() -> Result<()> {
-    let mut circle = Circle::new();
+    let mut circle = Circle::with_capacity(23);
     for i in 1..=22 {
-        circle.turn(i);
+        circle.turn(i, &GameRules::default());
     }
     assert_eq!(
         circle.get_vec(),
         vec![22, 11, 1, 12, 6, 13, 3, 14, 7, 15, 0, 16, 8, 17, 4, 18, 9, 19, 2, 20, 10, 21, 5]
     );
     println!("1-22 test passed.");
-    circle.turn(23);
+    circle.turn(23, &GameRules::default());
     assert_eq!(
         circle.get_vec(),
         vec![19, 2, 20, 10, 21, 5, 22, 11, 1, 12, 6, 13, 3, 14, 7, 15, 0, 16, 8, 17, 4, 18]
@@ -244,3 +289,121 @@ fn test_inputs() -> Result<()> {
     println!("tests passed!");
     Ok(())
 }
+
+// Simulates the circle with a Vec, moving marbles around on every insertion and removal, and
+// returns the score of each turn, and the circle starting from the current marble after each turn.
+
+#[cfg(test)]
+fn brute_force_turns(marbles: MarbleId, rules: &GameRules) -> Vec<(Score, Vec<MarbleId>)> {
+    let mut circle = vec![0];
+    let mut current = 0;
+    (1..=marbles)
+        .map(|new_id| {
+            let len = circle.len();
+            let score = if new_id.is_multiple_of(rules.scoring_modulus) {
+                if len == 1 {
+                    Score::from(new_id)
+                } else {
+                    let index = (current + len - rules.removal_offset % len) % len;
+                    let removed = circle.remove(index);
+                    current = index % circle.len();
+                    Score::from(new_id) + Score::from(removed)
+                }
+            } else {
+                current = (current + rules.insertion_offset) % len + 1;
+                circle.insert(current, new_id);
+                0
+            };
+            let mut rotated = circle.clone();
+            rotated.rotate_left(current);
+            (score, rotated)
+        })
+        .collect()
+}
+
+#[test]
+fn test_rules_match_brute_force() -> Result<()> {
+    let variants = [
+        "",
+        "modulus=5 removal=2",
+        "modulus=3 removal=10 insertion=0",
+        "modulus=1",
+        "modulus=0 insertion=4",
+        "modulus=7 removal=0 insertion=3",
+    ];
+    for variant in &variants {
+        let rules = variant.parse::<GameRules>()?;
+        let mut circle = Circle::with_capacity(200);
+        for (new_id, (score, expected)) in (1..).zip(brute_force_turns(200, &rules)) {
+            assert_eq!(circle.turn(new_id, &rules).0, score, "{}", variant);
+            assert_eq!(circle.get_vec(), expected, "{}", variant);
+        }
+    }
+
+    let game = "9 players; last marble is worth 25 points; modulus=5 removal=2".parse::<Game>()?;
+    assert_eq!(game.rules.scoring_modulus, 5);
+    assert!("9 players; last marble is worth 25 points; speed=2"
+        .parse::<Game>()
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_scoreboard() -> Result<()> {
+    let scoreboard = "9 players; last marble is worth 25 points"
+        .parse::<Game>()?
+        .with_history()
+        .play();
+    assert_eq!(
+        scoreboard.events,
+        vec![ScoringEvent {
+            turn: 23,
+            player: 4,
+            placed: 23,
+            removed: Some(9),
+            points: 32,
+        }]
+    );
+    assert_eq!(scoreboard.winner(), (4, 32));
+    assert_eq!(scoreboard.winner_took_lead, Some(23));
+
+    // Replay the events to check when the winner took the lead for good.
+    let scoreboard = "10 players; last marble is worth 1618 points"
+        .parse::<Game>()?
+        .with_history()
+        .play();
+    let (winner, score) = scoreboard.winner();
+    // The winner is the last player.
+    assert_eq!((winner, score), (9, 8317));
+    let mut scores = vec![0; 10];
+    let mut took_lead = None;
+    for event in &scoreboard.events {
+        scores[event.player] += event.points;
+        let is_only_leader =
+            (0..10).all(|player| player == winner || scores[player] < scores[winner]);
+        if !is_only_leader {
+            took_lead = None;
+        } else if took_lead.is_none() {
+            took_lead = Some(event.turn);
+        }
+    }
+    assert_eq!(scores, scoreboard.scores);
+    assert!(took_lead.is_some());
+    assert_eq!(scoreboard.winner_took_lead, took_lead);
+    assert_eq!(took_lead, Some(1610));
+
+    // The last of 4 players only scores once, on turn 92, which puts them in the lead.
+    let scoreboard = "4 players; last marble is worth 100 points"
+        .parse::<Game>()?
+        .play();
+    assert_eq!(scoreboard.winner(), (3, 107));
+    assert_eq!(scoreboard.winner_took_lead, Some(92));
+
+    // Without a history, only the scores are kept.
+    let scoreboard = "10 players; last marble is worth 1618 points"
+        .parse::<Game>()?
+        .play();
+    assert!(scoreboard.events.is_empty());
+    assert_eq!(scoreboard.winner_took_lead, took_lead);
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use crate::{Error, MarbleId, Result};
+use std::str::FromStr;
+
+// The rules for placing marbles:
+//  - a marble whose id is a multiple of the scoring modulus scores, instead of being placed, and
+//    the marble `removal_offset` counter-clockwise of the current marble is removed and scores too.
+//    A modulus of 0 means that marbles never score.
+//  - otherwise the marble is placed just after the marble `insertion_offset` clockwise of the
+//    current marble, where 0 places it right after the current marble.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GameRules {
+    pub scoring_modulus: MarbleId,
+    pub removal_offset: usize,
+    pub insertion_offset: usize,
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        GameRules {
+            scoring_modulus: 23,
+            removal_offset: 7,
+            insertion_offset: 1,
+        }
+    }
+}
+
+// Parses whitespace separated overrides of the default rules, eg: "modulus=19 removal=5", where
+// the keys are "modulus", "removal", and "insertion".
+
+impl FromStr for GameRules {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        s.split_whitespace()
+            .try_fold(GameRules::default(), |mut rules, token| {
+                let (key, value) = token
+                    .split_once('=')
+                    .ok_or_else(|| Error::from(format!("invalid game rule: {:?}", token)))?;
+                match key {
+                    "modulus" => rules.scoring_modulus = value.parse()?,
+                    "removal" => rules.removal_offset = value.parse()?,
+                    "insertion" => rules.insertion_offset = value.parse()?,
+                    _ => return Err(Error::from(format!("unknown game rule: {:?}", key))),
+                }
+                Ok(rules)
+            })
+    }
+}
+
+#[test]
+fn test_parse_rules() -> Result<()> {
+    assert_eq!("".parse::<GameRules>()?, GameRules::default());
+    assert_eq!(
+        "insertion=0 modulus=5".parse::<GameRules>()?,
+        GameRules {
+            scoring_modulus: 5,
+            removal_offset: 7,
+            insertion_offset: 0,
+        }
+    );
+    assert!("modulus".parse::<GameRules>().is_err());
+    assert!("speed=2".parse::<GameRules>().is_err());
+    assert!("removal=-1".parse::<GameRules>().is_err());
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use crate::Point;
+
+// Finds the time when the points are closest together, without stepping through every second.
+//
+// The spread of the points, measured by the sum of their squared distances from their centre, is
+// a quadratic in t, so it's smallest at t = -Σ(p·v) / Σ(v·v), where p and v are each point's
+// position and velocity relative to the centre. The message usually appears when the bounding box
+// is smallest instead, which is close by, so we finish with a search for the smallest bounding box
+// around that time. Its width plus height is a convex function of t, since the width and height
+// are both the max minus the min of linear functions of t.
+//
+// Returns None when all the points move together, so they never get any closer. Times are never
+// negative, since the message can't appear before the points start moving.
+
+pub fn find_convergence_time(points: &[Point]) -> Option<i64> {
+    let n = points.len() as f64;
+    let mean = |f: &dyn Fn(&Point) -> i64| points.iter().map(|p| f(p) as f64).sum::<f64>() / n;
+    let (mx, my) = (mean(&|p| p.x), mean(&|p| p.y));
+    let (mvx, mvy) = (mean(&|p| p.vx), mean(&|p| p.vy));
+
+    let (dot, speed) = points.iter().fold((0.0, 0.0), |(dot, speed), p| {
+        let (x, y) = (p.x as f64 - mx, p.y as f64 - my);
+        let (vx, vy) = (p.vx as f64 - mvx, p.vy as f64 - mvy);
+        (dot + x * vx + y * vy, speed + vx * vx + vy * vy)
+    });
+    if speed == 0.0 {
+        return None;
+    }
+    let estimate = (-dot / speed).round().max(0.0) as i64;
+    Some(minimise_convex(|t| get_size(points, t), estimate))
+}
+
+// Returns the width plus the height of the bounding box of the points at time t.
+
+fn get_size(points: &[Point], t: i64) -> i128 {
+    let (minx, maxx, miny, maxy) = points.iter().map(|p| p.at(t)).fold(
+        (i128::MAX, i128::MIN, i128::MAX, i128::MIN),
+        |(minx, maxx, miny, maxy), (x, y)| (minx.min(x), maxx.max(x), miny.min(y), maxy.max(y)),
+    );
+    (maxx - minx) + (maxy - miny)
+}
+
+// Returns the smallest t >= 0 where the convex function f is smallest, starting the search from
+// the given time. The search gallops away from the start until it brackets the minimum, and then
+// binary searches for where f stops decreasing, so it's fast even when the start is far off.
+
+fn minimise_convex(f: impl Fn(i64) -> i128, start: i64) -> i64 {
+    let is_rising = |t: i64| f(t + 1) >= f(t);
+
+    // Find lo where f is still falling (or 0), and hi where f has stopped falling.
+    let (mut lo, mut hi) = (start, start);
+    let mut step = 1;
+    if is_rising(start) {
+        while lo > 0 && is_rising(lo) {
+            hi = lo;
+            lo = (lo - step).max(0);
+            step *= 2;
+        }
+        if is_rising(lo) {
+            return lo;
+        }
+    } else {
+        while !is_rising(hi) {
+            lo = hi;
+            hi += step;
+            step *= 2;
+        }
+    }
+
+    // Now f is falling at lo, and not at hi.
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        if is_rising(mid) {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    hi
+}
+
+#[test]
+fn test_minimise_convex() {
+    for &target in &[0, 1, 7, 1000, 123_456_789] {
+        for &start in &[0, 3, 999, 5_000_000_000] {
+            let f = |t: i64| i128::from((t - target).abs()) * 3;
+            assert_eq!(minimise_convex(f, start), target);
+        }
+    }
+    // Plateaus resolve to their earliest time.
+    let f = |t: i64| i128::from((t - 10).max(0) + (5 - t).max(0));
+    assert_eq!(minimise_convex(f, 8), 5);
+    assert_eq!(minimise_convex(f, 0), 5);
+    assert_eq!(minimise_convex(f, 100), 5);
+}
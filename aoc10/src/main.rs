@@ -1,12 +1,17 @@
 #[macro_use]
 extern crate lazy_static;
 use regex::Regex;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use std::fmt::Display;
-use std::hash::{Hash, Hasher};
 use std::io::{Read, Write};
 use std::str::FromStr;
 
+mod converge;
+mod ocr;
+
+use converge::find_convergence_time;
+use ocr::read_message;
+
 type Error = std::boxed::Box<dyn std::error::Error>;
 type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -18,28 +23,32 @@ fn main() -> Result<()> {
     let (message, seconds) = grid.get_message()?;
     writeln!(std::io::stdout(), "\n\nmessage:\n{}", message)?;
     writeln!(std::io::stdout(), "achieved in {} seconds", seconds)?;
+    writeln!(std::io::stdout(), "text: {}", read_message(&message)?)?;
     Ok(())
 }
 
 struct Grid {
     points: Vec<Point>,
-    index: HashSet<(i32, i32)>, // index point's x/y coords for querying
+    index: HashSet<(i64, i64)>, // index point's x/y coords for querying
 }
 
 struct Bounds {
-    minx: i32,
-    maxx: i32,
-    miny: i32,
-    maxy: i32,
+    minx: i64,
+    maxx: i64,
+    miny: i64,
+    maxy: i64,
 }
 
 impl Grid {
-    // Update the points, and update the indexes for our Grid's points mapping
+    // Moves the points on by the given number of seconds, and updates the indexes for our Grid's
+    // points mapping
 
-    fn step(&mut self) {
+    fn advance(&mut self, seconds: i64) {
         let mut index = HashSet::new();
-        self.points.iter_mut().for_each(|mut point| {
-            point.step();
+        self.points.iter_mut().for_each(|point| {
+            let (x, y) = point.at(seconds);
+            point.x = x as i64;
+            point.y = y as i64;
             index.insert((point.x, point.y));
         });
         self.index = index;
@@ -49,10 +58,10 @@ impl Grid {
     fn get_bounds(&self) -> Bounds {
         self.points.iter().fold(
             Bounds {
-                minx: i32::max_value(),
-                maxx: i32::min_value(),
-                miny: i32::max_value(),
-                maxy: i32::min_value(),
+                minx: i64::MAX,
+                maxx: i64::MIN,
+                miny: i64::MAX,
+                maxy: i64::MIN,
             },
             |mut bounds, point| {
                 bounds.minx = std::cmp::min(bounds.minx, point.x);
@@ -64,19 +73,21 @@ impl Grid {
         )
     }
 
-    // iterates until we hit the message.
+    // Jumps straight to the time when the points are closest together, and checks that they form
+    // a message there.
     // Returns a tuple of the message string, and the number of seconds to reach the message.
 
-    fn get_message(mut self) -> Result<(String, u32)> {
-        for i in 1..1_000_000 {
-            self.step();
-            if self.message_found() {
-                return Ok((self.to_str(), i));
-            }
+    fn get_message(mut self) -> Result<(String, i64)> {
+        let seconds = find_convergence_time(&self.points)
+            .ok_or("The points all move together, so they never form a message!")?;
+        self.advance(seconds);
+        if !self.message_found() {
+            return Err(Error::from(format!(
+                "The points are closest together after {} seconds, but don't form a message!",
+                seconds
+            )));
         }
-        Err(Error::from(
-            "Unable to find a message after 1,000,000 seconds!",
-        ))
+        Ok((self.to_str(), seconds))
     }
 
     // Determine whether the message has been found, according to criteria where if there are no
@@ -163,19 +174,25 @@ impl Display for Grid {
 }
 
 struct Point {
-    x: i32,
-    y: i32,
-    vx: i8,
-    vy: i8,
+    x: i64,
+    y: i64,
+    vx: i64,
+    vy: i64,
 }
 
 impl Point {
-    fn step(&mut self) {
-        self.x = self.x.saturating_add(i32::from(self.vx));
-        self.y = self.y.saturating_add(i32::from(self.vy));
+    // Returns the position of the point after the given number of seconds. This is wider than the
+    // coordinates, so that far off times can't overflow.
+
+    fn at(&self, seconds: i64) -> (i128, i128) {
+        let t = i128::from(seconds);
+        (
+            i128::from(self.x) + i128::from(self.vx) * t,
+            i128::from(self.y) + i128::from(self.vy) * t,
+        )
     }
 
-    fn get_adjacent(&self) -> [(i32, i32); 8] {
+    fn get_adjacent(&self) -> [(i64, i64); 8] {
         [
             (self.x + 1, self.y),
             (self.x + 1, self.y + 1),
@@ -189,41 +206,44 @@ impl Point {
     }
 }
 
+#[cfg(test)]
+const EXAMPLE: &str = "\
+    position=< 9,  1> velocity=< 0,  2>
+    position=< 7,  0> velocity=<-1,  0>
+    position=< 3, -2> velocity=<-1,  1>
+    position=< 6, 10> velocity=<-2, -1>
+    position=< 2, -4> velocity=< 2,  2>
+    position=<-6, 10> velocity=< 2, -2>
+    position=< 1,  8> velocity=< 1, -1>
+    position=< 1,  7> velocity=< 1,  0>
+    position=<-3, 11> velocity=< 1, -2>
+    position=< 7,  6> velocity=<-1, -1>
+    position=<-2,  3> velocity=< 1,  0>
+    position=<-4,  3> velocity=< 2,  0>
+    position=<10, -3> velocity=<-1,  1>
+    position=< 5, 11> velocity=< 1, -2>
+    position=< 4,  7> velocity=< 0, -1>
+    position=< 8, -2> velocity=< 0,  1>
+    position=<15,  0> velocity=<-2,  0>
+    position=< 1,  6> velocity=< 1,  0>
+    position=< 8,  9> velocity=< 0, -1>
+    position=< 3,  3> velocity=<-1,  1>
+    position=< 0,  5> velocity=< 0, -1>
+    position=<-2,  2> velocity=< 2,  0>
+    position=< 5, -2> velocity=< 1,  2>
+    position=< 1,  4> velocity=< 2,  1>
+    position=<-2,  7> velocity=< 2, -2>
+    position=< 3,  6> velocity=<-1, -1>
+    position=< 5,  0> velocity=< 1,  0>
+    position=<-6,  0> velocity=< 2,  0>
+    position=< 5,  9> velocity=< 1, -2>
+    position=<14,  7> velocity=<-2,  0>
+    position=<-3,  6> velocity=< 2, -1>\
+    ";
+
 #[test]
 fn test_message() -> Result<()> {
-    let input = "\
-        position=< 9,  1> velocity=< 0,  2>
-        position=< 7,  0> velocity=<-1,  0>
-        position=< 3, -2> velocity=<-1,  1>
-        position=< 6, 10> velocity=<-2, -1>
-        position=< 2, -4> velocity=< 2,  2>
-        position=<-6, 10> velocity=< 2, -2>
-        position=< 1,  8> velocity=< 1, -1>
-        position=< 1,  7> velocity=< 1,  0>
-        position=<-3, 11> velocity=< 1, -2>
-        position=< 7,  6> velocity=<-1, -1>
-        position=<-2,  3> velocity=< 1,  0>
-        position=<-4,  3> velocity=< 2,  0>
-        position=<10, -3> velocity=<-1,  1>
-        position=< 5, 11> velocity=< 1, -2>
-        position=< 4,  7> velocity=< 0, -1>
-        position=< 8, -2> velocity=< 0,  1>
-        position=<15,  0> velocity=<-2,  0>
-        position=< 1,  6> velocity=< 1,  0>
-        position=< 8,  9> velocity=< 0, -1>
-        position=< 3,  3> velocity=<-1,  1>
-        position=< 0,  5> velocity=< 0, -1>
-        position=<-2,  2> velocity=< 2,  0>
-        position=< 5, -2> velocity=< 1,  2>
-        position=< 1,  4> velocity=< 2,  1>
-        position=<-2,  7> velocity=< 2, -2>
-        position=< 3,  6> velocity=<-1, -1>
-        position=< 5,  0> velocity=< 1,  0>
-        position=<-6,  0> velocity=< 2,  0>
-        position=< 5,  9> velocity=< 1, -2>
-        position=<14,  7> velocity=<-2,  0>
-        position=<-3,  6> velocity=< 2, -1>\
-    ";
+    let input = EXAMPLE;
     let grid = input.parse::<Grid>()?;
     assert_eq!(grid.points.len(), 31);
     assert_eq!(
@@ -246,3 +266,34 @@ fn test_message() -> Result<()> {
     println!("message test passed.");
     Ok(())
 }
+
+#[test]
+fn test_far_off_message() -> Result<()> {
+    // Rewind the example by billions of seconds.
+    let rewind = 5_000_000_000;
+    let mut grid = EXAMPLE.parse::<Grid>()?;
+    for point in &mut grid.points {
+        point.x -= point.vx * rewind;
+        point.y -= point.vy * rewind;
+    }
+    let (message, seconds) = grid.get_message()?;
+    assert_eq!(seconds, rewind + 3);
+    assert!(message.starts_with("#...#..###\n"));
+
+    let grid = "\
+        position=< 1,  2> velocity=< 3, -1>
+        position=< 5,  0> velocity=< 3, -1>\
+    "
+    .parse::<Grid>()?;
+    assert!(grid.get_message().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_read_input_message() -> Result<()> {
+    let grid = std::fs::read_to_string("input/input.txt")?.parse::<Grid>()?;
+    let (message, seconds) = grid.get_message()?;
+    assert_eq!(read_message(&message)?, "RECLRNZE");
+    assert_eq!(seconds, 10007);
+    Ok(())
+}
@@ -0,0 +1,151 @@
+use std::fmt::{Display, Formatter};
+
+// The letters of the 6x10 font that the messages are written in, where '#' marks a star. Letters
+// are separated by at least one empty column.
+
+const GLYPH_WIDTH: usize = 6;
+const GLYPH_HEIGHT: usize = 10;
+
+const FONT_LETTERS: &str = "ABCEFGHJKLNPRXZ";
+const FONT: &str = "\
+..##....#####....####...######..######...####...#....#.....###..#....#..#.......#....#..#####...#####...#....#..######
+.#..#...#....#..#....#..#.......#.......#....#..#....#......#...#...#...#.......##...#..#....#..#....#..#....#.......#
+#....#..#....#..#.......#.......#.......#.......#....#......#...#..#....#.......##...#..#....#..#....#...#..#........#
+#....#..#....#..#.......#.......#.......#.......#....#......#...#.#.....#.......#.#..#..#....#..#....#...#..#.......#.
+#....#..#####...#.......#####...#####...#.......######......#...##......#.......#.#..#..#####...#####.....##.......#..
+######..#....#..#.......#.......#.......#..###..#....#......#...##......#.......#..#.#..#.......#..#......##......#...
+#....#..#....#..#.......#.......#.......#....#..#....#......#...#.#.....#.......#..#.#..#.......#...#....#..#....#....
+#....#..#....#..#.......#.......#.......#....#..#....#..#...#...#..#....#.......#...##..#.......#...#....#..#...#.....
+#....#..#....#..#....#..#.......#.......#...##..#....#..#...#...#...#...#.......#...##..#.......#....#..#....#..#.....
+#....#..#####....####...######..#........###.#..#....#...###....#....#..######..#....#..#.......#....#..#....#..######";
+
+// Returns each letter of the font, along with its rows.
+
+fn get_font() -> Vec<(char, Vec<&'static str>)> {
+    FONT_LETTERS
+        .chars()
+        .enumerate()
+        .map(|(i, letter)| {
+            let from = i * (GLYPH_WIDTH + 2);
+            let rows = FONT
+                .lines()
+                .map(|row| &row[from..from + GLYPH_WIDTH])
+                .collect();
+            (letter, rows)
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+pub enum OcrError {
+    // The picture isn't as tall as the letters of the font
+    WrongHeight { height: usize },
+    // The glyph at the given position isn't a letter of the font, with its rows joined by newlines
+    UnknownGlyph { position: usize, glyph: String },
+}
+
+impl Display for OcrError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OcrError::WrongHeight { height } => write!(
+                f,
+                "the message is {} rows tall, but letters are {} rows tall",
+                height, GLYPH_HEIGHT
+            ),
+            OcrError::UnknownGlyph { position, glyph } => {
+                write!(f, "unknown glyph at position {}:\n{}", position, glyph)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OcrError {}
+
+// Reads the letters of a picture made of '#' and '.' rows, like the one from `Grid::to_str`.
+
+pub fn read_message(picture: &str) -> Result<String, OcrError> {
+    let rows: Vec<&[u8]> = picture.lines().map(str::as_bytes).collect();
+    if rows.len() != GLYPH_HEIGHT {
+        return Err(OcrError::WrongHeight { height: rows.len() });
+    }
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let is_empty_column = |x: usize| rows.iter().all(|row| row.get(x) != Some(&b'#'));
+
+    // Split the picture into glyphs at the empty columns.
+    let mut glyphs: Vec<(usize, usize)> = vec![];
+    let mut start = None;
+    for x in 0..=width {
+        match (start, x == width || is_empty_column(x)) {
+            (None, false) => start = Some(x),
+            (Some(from), true) => {
+                glyphs.push((from, x));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    glyphs
+        .into_iter()
+        .enumerate()
+        .map(|(position, (from, to))| {
+            let glyph: Vec<String> = rows
+                .iter()
+                .map(|row| {
+                    (from..to)
+                        .map(|x| if row.get(x) == Some(&b'#') { '#' } else { '.' })
+                        .collect()
+                })
+                .collect();
+            read_glyph(&glyph).ok_or_else(|| OcrError::UnknownGlyph {
+                position,
+                glyph: glyph.join("\n"),
+            })
+        })
+        .collect()
+}
+
+fn read_glyph(glyph: &[String]) -> Option<char> {
+    if glyph.iter().any(|row| row.len() != GLYPH_WIDTH) {
+        return None;
+    }
+    get_font()
+        .into_iter()
+        .find(|(_, rows)| rows.iter().zip(glyph).all(|(a, b)| a == b))
+        .map(|(letter, _)| letter)
+}
+
+#[test]
+fn test_read_message() {
+    assert_eq!(read_message(FONT), Ok(String::from(FONT_LETTERS)));
+
+    // Wider gaps, and rows that don't reach the last column.
+    let r = &get_font()[12].1;
+    let picture: Vec<String> = r
+        .iter()
+        .map(|row| format!("...{}....{}", row, row.trim_end_matches('.')))
+        .collect();
+    assert_eq!(read_message(&picture.join("\n")), Ok(String::from("RR")));
+
+    // An 'L' with a stray star.
+    let font = get_font();
+    let mut l = font[9].1.clone();
+    l[3] = "#.#...";
+    let picture: Vec<String> = font[0]
+        .1
+        .iter()
+        .zip(&l)
+        .map(|(a, l)| format!("{}..{}", a, l))
+        .collect();
+    assert_eq!(
+        read_message(&picture.join("\n")),
+        Err(OcrError::UnknownGlyph {
+            position: 1,
+            glyph: l.join("\n"),
+        })
+    );
+    assert_eq!(
+        read_message("#\n#"),
+        Err(OcrError::WrongHeight { height: 2 })
+    );
+}
@@ -1,13 +1,23 @@
-use std::cmp;
+use rayon::prelude::*;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::error;
-use std::io::Write;
+use std::io::{Read, Write};
+use std::ops::RangeInclusive;
 use std::result;
+use std::str::FromStr;
+
+mod summed;
+
+use summed::SummedAreaTable;
 
 type Error = std::boxed::Box<dyn error::Error>;
 type Result<R, E = Error> = result::Result<R, E>;
 
 fn main() -> Result<()> {
-    let grid = Grid::new(4455);
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input)?;
+    let grid = input.parse::<Grid>()?;
 
     let (x, y) = grid.find_largest_3x3();
     writeln!(
@@ -17,126 +27,214 @@ fn main() -> Result<()> {
         y
     )?;
 
+    let largest = grid.find_largest();
     writeln!(
         std::io::stdout(),
-        "location and size of largest grid: {:?}",
-        grid.find_largest()?
+        "location and size of largest grid: {},{},{}",
+        largest.x,
+        largest.y,
+        largest.size
     )?;
+
+    let max_size = grid.width.min(grid.height);
+    for square in grid.top_squares(3, 1..=max_size) {
+        writeln!(
+            std::io::stdout(),
+            "top square: {},{},{} with power {}",
+            square.x,
+            square.y,
+            square.size,
+            square.power
+        )?;
+    }
     Ok(())
 }
 
 type PowerLevel = i32;
+type SerialNumber = u32;
 
 struct Grid {
-    cells: [[PowerLevel; 300]; 300],
+    width: usize,
+    height: usize,
+    cells: Vec<PowerLevel>,
 }
 
-impl Grid {
-    fn new(serial_number: u16) -> Self {
-        let mut cells = [[0; 300]; 300];
+// A square of cells, where (x, y) is its top left corner, starting from 1 like the puzzle's
+// coordinates.
 
-        for y in 0..300 {
-            for x in 0..300 {
-                cells[x][y] = Grid::get_power_level(serial_number, x as u16, y as u16);
-            }
+#[derive(Debug, PartialEq)]
+struct Square {
+    x: usize,
+    y: usize,
+    size: usize,
+    power: PowerLevel,
+}
+
+impl Grid {
+    fn new(serial_number: SerialNumber, width: usize, height: usize) -> Self {
+        let cells = (0..height)
+            .flat_map(|y| (0..width).map(move |x| Grid::get_power_level(serial_number, x, y)))
+            .collect();
+        Grid {
+            width,
+            height,
+            cells,
         }
-        Grid { cells }
     }
 
-    // Scan the grid to find the 3x3 sub-grid with the largest sum.
-    // Returns the coordinates of the sub-grid's top left corner.
+    // Returns the power level of a cell, where coordinates start from 0.
+
+    fn get(&self, x: usize, y: usize) -> PowerLevel {
+        self.cells[y * self.width + x]
+    }
+
+    // Scan the grid to find the 3x3 sub-grid with the largest sum, where ties go to the topmost,
+    // then the leftmost. Returns the coordinates of the sub-grid's top left corner.
 
     fn find_largest_3x3(&self) -> (usize, usize) {
-        // calculate the value of the current 3x3 grid.
-        // let mut max = (0..3).fold(0, |sum, y| {
-        //     sum + (0..3).fold(0, |sum, x| sum + self.cells[x][y])
-        // });
-        let mut max = std::i32::MIN;
-        // let mut curr_sum = max;
-        let mut max_coords = (0, 0);
-
-        for y in 0..=297 {
-            let mut curr_sum = (y..=y + 2).fold(0, |sum, y| {
-                sum + (0..=2).fold(0, |sum, x| sum + self.cells[x][y])
-            });
-            for x in 1..=297 {
-                // subtract the value of the left-most col
-                curr_sum -= (y..=y + 2).fold(0, |sum, y| sum + self.cells[x - 1][y]);
-
-                // add the value of the right-most col
-                curr_sum += (y..=y + 2).fold(0, |sum, y| sum + self.cells[x + 2][y]);
-
-                if curr_sum > max {
-                    max_coords = (x, y);
-                    max = curr_sum;
-                }
-            }
-        }
+        let table = SummedAreaTable::new(self);
+        let (x, y) = (0..=self.height - 3)
+            .flat_map(|y| (0..=self.width - 3).map(move |x| (x, y)))
+            .max_by_key(|&(x, y)| (table.square_power(x, y, 3), Reverse(y), Reverse(x)))
+            .expect("the grid must be at least 3x3");
+        (x + 1, y + 1)
+    }
 
-        (max_coords.0 + 1, max_coords.1 + 1)
+    // Scan the grid to find the square sub-grid with the largest sum, out of every square of
+    // every size. Ties go to the smallest square, then the topmost, then the leftmost.
+
+    fn find_largest(&self) -> Square {
+        let table = SummedAreaTable::new(self);
+        let rank = |square: &Square| {
+            (
+                square.power,
+                Reverse(square.size),
+                Reverse(square.y),
+                Reverse(square.x),
+            )
+        };
+
+        // Each row of top left corners is searched in parallel, for every size of square that
+        // fits below and to the right of the corner.
+        (0..self.height)
+            .into_par_iter()
+            .filter_map(|y| {
+                (0..self.width)
+                    .flat_map(|x| {
+                        let max_size = (self.width - x).min(self.height - y);
+                        (1..=max_size).map(move |size| (x, size))
+                    })
+                    .map(|(x, size)| Square {
+                        x: x + 1,
+                        y: y + 1,
+                        size,
+                        power: table.square_power(x, y, size),
+                    })
+                    .max_by_key(rank)
+            })
+            .max_by_key(rank)
+            .expect("the grid must not be empty")
     }
 
-    // Scan the grid to find the square sub-grid with the largest sum.
-    // Returns the coordinates of the sub-grid's top left corner, along with the size of the
-    // sub-grid.
-
-    fn find_largest(&self) -> Result<(usize, usize, usize)> {
-        // create a summed area table: https://en.wikipedia.org/wiki/Summed-area_table
-        let mut sums: [[i32; 300]; 300] = [[0; 300]; 300];
-        for y in 0..300 {
-            for x in 0..300 {
-                let top = if y == 0 { 0 } else { sums[x][y - 1] };
-                let left = if x == 0 { 0 } else { sums[x - 1][y] };
-                let top_left = if x == 0 || y == 0 {
-                    0
-                } else {
-                    sums[x - 1][y - 1]
-                };
-                sums[x][y] = i32::from(self.cells[x][y]) + top + left - top_left;
-            }
-        }
+    // Returns the k squares with the most power, out of the squares with the given sizes, from
+    // most to least powerful. Squares tied with the last of the k squares are all included, so
+    // there may be more than k squares. Ties are ordered by size, then by y, then by x.
+    //
+    // The squares are swept once, keeping the k most powerful so far in a min-heap, along with
+    // any others tied with the least powerful of those, so memory only grows with k and the ties.
 
-        let mut max_sum = std::i32::MIN;
-        let mut results = (0, 0, 0);
-        let mut found_dupe = false;
-        for ymin in 0..300 {
-            for xmin in 0..300 {
-                for (xmax, ymax) in ((xmin + 1)..300).zip((ymin + 1)..300) {
-                    let length = xmax - xmin;
-                    // calculates the grid's sum, leveraging properties of the summed area table:
-                    let curr_sum =
-                        sums[xmax][ymax] - sums[xmin][ymax] - sums[xmax][ymin] + sums[xmin][ymin];
-                    if curr_sum > max_sum {
-                        // Add 1 to account for the 1-based indexing expected from the results
-                        // Add another 1 to account for xmin and ymin not being in the bounds of the sub-grid.
-                        results = (xmin + 2, ymin + 2, length);
-                        max_sum = curr_sum;
-                        found_dupe = false;
-                    } else if curr_sum == max_sum {
-                        found_dupe = true;
+    fn top_squares(&self, k: usize, sizes: RangeInclusive<usize>) -> Vec<Square> {
+        if k == 0 {
+            return vec![];
+        }
+        let table = SummedAreaTable::new(self);
+        let max_size = self.width.min(self.height);
+        let sizes = (*sizes.start()).max(1)..=(*sizes.end()).min(max_size);
+
+        // Squares as (power, size, y, x), with coordinates starting from 0.
+        let mut heap: BinaryHeap<Reverse<(PowerLevel, usize, usize, usize)>> = BinaryHeap::new();
+        let mut ties = vec![];
+        for size in sizes {
+            for y in 0..=self.height - size {
+                for x in 0..=self.width - size {
+                    let square = (table.square_power(x, y, size), size, y, x);
+                    let threshold = match heap.peek() {
+                        Some(Reverse(least)) if heap.len() == k => least.0,
+                        _ => {
+                            heap.push(Reverse(square));
+                            continue;
+                        }
+                    };
+                    if square.0 == threshold {
+                        ties.push(square);
+                    } else if square.0 > threshold {
+                        heap.push(Reverse(square));
+                        let Reverse(dropped) = heap.pop().unwrap();
+                        let Reverse(least) = heap.peek().unwrap();
+                        if least.0 == dropped.0 {
+                            ties.push(dropped);
+                        } else {
+                            ties.clear();
+                        }
                     }
                 }
             }
         }
 
-        if found_dupe {
-            return Err(Error::from(format!(
-                "Not supposed to have more than one sum {:?}",
-                results
-            )));
-        }
-
-        Ok(results)
+        let mut top: Vec<Square> = heap
+            .into_iter()
+            .map(|Reverse(square)| square)
+            .chain(ties)
+            .map(|(power, size, y, x)| Square {
+                x: x + 1,
+                y: y + 1,
+                size,
+                power,
+            })
+            .collect();
+        top.sort_by_key(|square| (Reverse(square.power), square.size, square.y, square.x));
+        top
     }
 
-    fn get_power_level(serial_number: u16, x: u16, y: u16) -> i32 {
+    fn get_power_level(serial_number: SerialNumber, x: usize, y: usize) -> PowerLevel {
         // add 1 to x and y to account for 1-based indexing
-        let rack_id = i32::from(x + 1) + 10;
-        let power_level = ((rack_id * i32::from(y + 1)) + i32::from(serial_number)) * rack_id;
+        let rack_id = x as i64 + 1 + 10;
+        let power_level = ((rack_id * (y as i64 + 1)) + i64::from(serial_number)) * rack_id;
 
         // Keep only the hundreds digit of the power level (so 12345 becomes 3; numbers with no
         // hundreds digit become 0)
-        (power_level / 100) % 10 - 5
+        ((power_level / 100) % 10 - 5) as PowerLevel
+    }
+}
+
+// Parses the grid serial number, optionally followed by the grid size, eg: "4455" or
+// "4455 300x300". Grids are 300x300 by default.
+
+impl FromStr for Grid {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens = s.split_whitespace();
+        let serial_number = tokens.next().ok_or("missing grid serial number")?.parse()?;
+        let (width, height) = match tokens.next() {
+            Some(size) => {
+                let (width, height) = size
+                    .split_once('x')
+                    .ok_or_else(|| Error::from(format!("invalid grid size: {:?}", size)))?;
+                (width.parse()?, height.parse()?)
+            }
+            None => (300, 300),
+        };
+        if let Some(token) = tokens.next() {
+            return Err(Error::from(format!("unexpected input: {:?}", token)));
+        }
+        if width < 3 || height < 3 {
+            return Err(Error::from(format!(
+                "grid must be at least 3x3, not {}x{}",
+                width, height
+            )));
+        }
+        Ok(Grid::new(serial_number, width, height))
     }
 }
 
@@ -154,10 +252,10 @@ fn test_power_cells() -> Result<()> {
 
 #[test]
 fn test_grid_find_3x3() -> Result<()> {
-    let grid = Grid::new(18);
+    let grid = Grid::new(18, 300, 300);
     assert_eq!(grid.find_largest_3x3(), (33, 45));
 
-    let grid = Grid::new(42);
+    let grid = Grid::new(42, 300, 300);
     assert_eq!(grid.find_largest_3x3(), (21, 61));
     println!("test grid find 3x3 passed.");
     Ok(())
@@ -167,18 +265,35 @@ fn test_grid_find_3x3() -> Result<()> {
 fn test_grid_find_largest() -> Result<()> {
     // For grid serial number 18, the largest total square (with a total power of 113) is 16x16 and
     // has a top-left corner of 90,269, so its identifier is 90,269,16.
-    let grid = Grid::new(18);
-    assert_eq!(grid.find_largest()?, (90, 269, 16));
+    let grid = Grid::new(18, 300, 300);
+    assert_eq!(
+        grid.find_largest(),
+        Square {
+            x: 90,
+            y: 269,
+            size: 16,
+            power: 113
+        }
+    );
 
     // For grid serial number 42, the largest total square (with a total power of 119) is 12x12 and
     // has a top-left corner of 232,251, so its identifier is 232,251,12.
-    let grid = Grid::new(42);
-    assert_eq!(grid.find_largest()?, (232, 251, 12));
+    let grid = Grid::new(42, 300, 300);
+    assert_eq!(
+        grid.find_largest(),
+        Square {
+            x: 232,
+            y: 251,
+            size: 12,
+            power: 119
+        }
+    );
     println!("test find_largest passed.");
     Ok(())
 }
 
 // This function borrows a slice
+#[cfg(test)]
 fn analyze_slice(slice: &[i32]) {
     println!("first element of the slice: {}", slice[0]);
     println!("the slice has {} elements", slice.len());
@@ -208,7 +323,6 @@ fn test_array_slicing() {
     assert_eq!(ys.len(), 10);
     assert_eq!(ys[0].len(), 10);
 
-
     // Indexing starts at 0
     // println!("first element of the array: {}", xs[0]);
     // println!("second element of the array: {}", xs[1]);
@@ -232,3 +346,118 @@ fn test_array_slicing() {
 
     println!("array slicing tests passed");
 }
+
+#[test]
+fn test_top_squares() -> Result<()> {
+    let grid = "18 300x300".parse::<Grid>()?;
+    let top = grid.top_squares(1, 3..=3);
+    assert_eq!(
+        top,
+        vec![Square {
+            x: 33,
+            y: 45,
+            size: 3,
+            power: 29
+        }]
+    );
+
+    // Every cell of a 1x1 square has a power between -5 and 4, so there are lots of ties.
+    let grid = "18 10x7".parse::<Grid>()?;
+    let top = grid.top_squares(2, 1..=1);
+    assert!(top.len() > 2);
+    assert!(top.iter().all(|square| square.power == 4));
+    let brute_force = (0..7)
+        .flat_map(|y| (0..10).map(move |x| (x, y)))
+        .filter(|&(x, y)| grid.get(x, y) == 4)
+        .count();
+    assert_eq!(top.len(), brute_force);
+
+    // Sizes beyond the grid are ignored.
+    let top = grid.top_squares(10, 7..=100);
+    assert_eq!(top.len(), 4);
+    assert!(top.iter().all(|square| square.size == 7));
+    assert!(grid.top_squares(0, 1..=7).is_empty());
+
+    assert!("18 2x300".parse::<Grid>().is_err());
+    assert!("18 300".parse::<Grid>().is_err());
+    assert!("".parse::<Grid>().is_err());
+    Ok(())
+}
+
+// Returns every square of the grid, with its power summed cell by cell.
+
+#[cfg(test)]
+fn brute_force_squares(grid: &Grid) -> Vec<Square> {
+    let mut squares = vec![];
+    for size in 1..=grid.width.min(grid.height) {
+        for y in 0..=grid.height - size {
+            for x in 0..=grid.width - size {
+                let power = (y..y + size)
+                    .flat_map(|cy| (x..x + size).map(move |cx| (cx, cy)))
+                    .map(|(cx, cy)| grid.get(cx, cy))
+                    .sum();
+                squares.push(Square {
+                    x: x + 1,
+                    y: y + 1,
+                    size,
+                    power,
+                });
+            }
+        }
+    }
+    squares
+}
+
+#[test]
+fn test_against_brute_force() {
+    let grids = [
+        (18, 30, 20),
+        (42, 17, 25),
+        (4455, 12, 12),
+        (7, 3, 9),
+        (30, 4, 3),
+        (5, 9, 3),
+        (18, 3, 3),
+    ];
+    for &(serial_number, width, height) in &grids {
+        let grid = Grid::new(serial_number, width, height);
+        let mut squares = brute_force_squares(&grid);
+        squares.sort_by_key(|square| (Reverse(square.power), square.size, square.y, square.x));
+
+        assert_eq!(grid.find_largest(), squares[0]);
+
+        let largest_3x3 = squares.iter().find(|square| square.size == 3).unwrap();
+        assert_eq!(
+            grid.find_largest_3x3(),
+            (largest_3x3.x, largest_3x3.y),
+            "{}x{} grid {}",
+            width,
+            height,
+            serial_number
+        );
+
+        let top = grid.top_squares(5, 1..=width.min(height));
+        let threshold = squares[4].power;
+        let expected: Vec<&Square> = squares
+            .iter()
+            .take_while(|square| square.power >= threshold)
+            .collect();
+        assert_eq!(top.iter().collect::<Vec<&Square>>(), expected);
+    }
+
+    // A grid where the whole grid is the largest square.
+    let grid = Grid {
+        width: 4,
+        height: 4,
+        cells: vec![1; 16],
+    };
+    assert_eq!(
+        grid.find_largest(),
+        Square {
+            x: 1,
+            y: 1,
+            size: 4,
+            power: 16
+        }
+    );
+}
@@ -0,0 +1,41 @@
+use crate::{Grid, PowerLevel};
+
+// A summed-area table of the grid: https://en.wikipedia.org/wiki/Summed-area_table
+//
+// The table has an extra row and column of zeros at the top and left, so that `sums[x][y]` is the
+// total power of all the cells above and to the left of (x, y), not including (x, y) itself. This
+// way the total of any square is just four lookups, without any special cases at the edges.
+
+pub struct SummedAreaTable {
+    width: usize,
+    sums: Vec<PowerLevel>,
+}
+
+impl SummedAreaTable {
+    pub fn new(grid: &Grid) -> Self {
+        let width = grid.width + 1;
+        let mut sums = vec![0; width * (grid.height + 1)];
+        for y in 0..grid.height {
+            for x in 0..grid.width {
+                sums[(y + 1) * width + x + 1] =
+                    grid.get(x, y) + sums[y * width + x + 1] + sums[(y + 1) * width + x]
+                        - sums[y * width + x];
+            }
+        }
+        SummedAreaTable { width, sums }
+    }
+
+    // Returns the total power of the cells above and to the left of (x, y), exclusive.
+
+    pub fn get(&self, x: usize, y: usize) -> PowerLevel {
+        self.sums[y * self.width + x]
+    }
+
+    // Returns the total power of the square with its top left corner at (x, y), where coordinates
+    // start from 0.
+
+    pub fn square_power(&self, x: usize, y: usize, size: usize) -> PowerLevel {
+        self.get(x + size, y + size) - self.get(x, y + size) - self.get(x + size, y)
+            + self.get(x, y)
+    }
+}
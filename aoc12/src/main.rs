@@ -1,10 +1,17 @@
 use std::boxed;
-use std::collections::HashSet;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
 use std::error;
 use std::io::{Read, Write};
 use std::result;
 use std::str::FromStr;
 
+mod row;
+mod rules;
+
+use row::Row;
+use rules::Rules;
+
 type Error = boxed::Box<dyn error::Error>;
 type Result<T, E = Error> = result::Result<T, E>;
 
@@ -12,167 +19,162 @@ fn main() -> Result<()> {
     let mut input = String::new();
     std::io::stdin().read_to_string(&mut input)?;
 
-    let simulation = input.parse::<Simulation>()?;
+    let mut simulation = input.parse::<Simulation>()?;
+    writeln!(
+        std::io::stdout(),
+        "rules: {} pots wide, {} patterns grow a plant, {} patterns have no rule",
+        simulation.rules.width(),
+        simulation.rules.count(),
+        simulation.rules.missing()
+    )?;
     writeln!(
         std::io::stdout(),
         "count after 20 generations: {}",
-        simulation.run(20)
+        simulation.sum_after(20).ok_or("no repeating pattern")?
     )?;
 
-    // after running this code:
-
-    // let simulation = input.parse::<Simulation>()?;
-    // writeln!(
-    //     std::io::stdout(),
-    //     "count after 50_000_000_000 generations: {}",
-    //     simulation.run(50_000_000_000)
-    // )?;
-
-    // we get:
-    // on generation: 500, sum is: 21684
-    // on generation: 5000, sum is: 201684
-    // on generation: 50000, sum is: 2001684
-    // on generation: 500000, sum is: 20001684
-
-    // the value is 2x1684 where x is a series of 0's.
-    // for 500, x is 0
-    // for 5000, x is 1
-    // for 50_000, x is 2
-    // for 500_000, x is 3
+    if let Some(cycle) = simulation.find_cycle() {
+        writeln!(
+            std::io::stdout(),
+            "pattern repeats every {} generations from generation {}, shifting by {} each time",
+            cycle.period,
+            cycle.first,
+            cycle.shift
+        )?;
+    }
 
-    // thus, if n is the number of zeros in our generation, x is n - 2.
-    // for 50 B, n is 10, so x is 8.
-    // thus, the result is 2000000001684
+    let count = simulation.sum_after(50_000_000_000).ok_or_else(|| {
+        format!(
+            "no repeating pattern within {} generations",
+            simulation.max_generations
+        )
+    })?;
+    writeln!(
+        std::io::stdout(),
+        "count after 50_000_000_000 generations: {}",
+        count
+    )?;
 
     Ok(())
 }
 
 type PotId = i64;
 
+// Simulations give up looking for a repeating pattern after this many generations by default.
+const MAX_GENERATIONS: u64 = 10_000;
+
+// The pattern of pots at generation first + period was the same as at generation first, but
+// shifted along by the given number of pots. From then on, the pattern repeats every period
+// generations, shifting along by the same amount each time.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Cycle {
+    first: u64,
+    period: u64,
+    shift: PotId,
+}
+
 struct Simulation {
-    pots: HashSet<PotId>, // a set of pot id's that have plants
-    matches: HashSet<String>,
+    row: Row,
+    rules: Rules,
     generation: u64,
+    max_generations: u64,
+    // The generation each pattern was first seen at, along with its left-most plant then.
+    seen: HashMap<Vec<u64>, (u64, Option<PotId>)>,
+    // The sum of the ids of the pots with plants, and the number of plants, at every generation
+    // so far.
+    history: Vec<(i64, i64)>,
+    cycle: Option<Cycle>,
 }
 
 impl Simulation {
-    // Run for a single generation.
+    fn new(row: Row, rules: Rules) -> Self {
+        let mut simulation = Simulation {
+            row,
+            rules,
+            generation: 0,
+            max_generations: MAX_GENERATIONS,
+            seen: HashMap::new(),
+            history: vec![],
+            cycle: None,
+        };
+        simulation.record();
+        simulation
+    }
 
-    fn run_generation(&mut self) {
+    // Run for a single generation, returning the previous generation's row.
+
+    fn run_generation(&mut self) -> Row {
         self.generation += 1;
+        let next = self.row.step(&self.rules);
+        let previous = std::mem::replace(&mut self.row, next);
+        self.record();
+        previous
+    }
+
+    // Adds the current generation to the history, and checks whether its pattern has been seen
+    // before, until the first repeat is found.
 
-        // if there are no pots, there is nothing to do.
-        if let (Some(left_most), Some(right_most)) =
-            (self.pots.iter().min(), self.pots.iter().max())
-        {
-            // Iterate over all relevant pots, starting 2 pots down from the left-most planted pot,
-            // ending 2 pots up from the right-most planted pot
-
-            let mut next_pots = HashSet::new();
-            for pot_id in (left_most - 2)..=(right_most + 2) {
-                // build up a pattern of plant distributions for the current PotId:
-                let pattern = ((pot_id - 2)..=(pot_id + 2))
-                    .map(|pot_id| {
-                        if self.pots.contains(&pot_id) {
-                            '#'
-                        } else {
-                            '.'
-                        }
-                    })
-                    .collect::<String>();
-                if self.matches.contains(&pattern) {
-                    next_pots.insert(pot_id);
-                }
+    fn record(&mut self) {
+        self.history.push((self.row.sum(), self.row.count() as i64));
+        if self.cycle.is_some() {
+            return;
+        }
+        match self.seen.entry(self.row.pattern().to_vec()) {
+            Entry::Occupied(entry) => {
+                let &(first, start) = entry.get();
+                // With no plants left, nothing will ever change again.
+                let shift = match (start, self.row.start()) {
+                    (Some(first_left), Some(current_left)) => current_left - first_left,
+                    _ => 0,
+                };
+                self.cycle = Some(Cycle {
+                    first,
+                    period: self.generation - first,
+                    shift,
+                });
+                // The patterns aren't needed any more.
+                self.seen = HashMap::new();
+            }
+            Entry::Vacant(entry) => {
+                entry.insert((self.generation, self.row.start()));
             }
-            self.pots = next_pots;
         }
     }
 
-    // Run simulation for 20 generations, returning the score at the end of the 20th generation.
+    // Runs until a pattern of pots repeats, giving up after the maximum number of generations.
 
-    fn run(mut self, generations: u64) -> i64 {
-        while self.generation < generations {
-            if self.generation == 500 {
-                println!(
-                    "on generation: {}, sum is: {}",
-                    self.generation,
-                    self.pots.iter().sum::<i64>()
-                );
-            }
-            if self.generation == 5000 {
-                println!(
-                    "on generation: {}, sum is: {}",
-                    self.generation,
-                    self.pots.iter().sum::<i64>()
-                );
-            }
-            if self.generation == 50_000 {
-                println!(
-                    "on generation: {}, sum is: {}",
-                    self.generation,
-                    self.pots.iter().sum::<i64>()
-                );
-            }
-            if self.generation == 500_000 {
-                println!(
-                    "on generation: {}, sum is: {}",
-                    self.generation,
-                    self.pots.iter().sum::<i64>()
-                );
-            }
-            if self.generation == 5_000_000 {
-                println!(
-                    "on generation: {}, sum is: {}",
-                    self.generation,
-                    self.pots.iter().sum::<i64>()
-                );
-            }
-            if self.generation == 50_000_000 {
-                println!(
-                    "on generation: {}, sum is: {}",
-                    self.generation,
-                    self.pots.iter().sum::<i64>()
-                );
-            }
-            if self.generation == 500_000_000 {
-                println!(
-                    "on generation: {}, sum is: {}",
-                    self.generation,
-                    self.pots.iter().sum::<i64>()
-                );
-            }
-            if self.generation == 5_000_000_000 {
-                println!(
-                    "on generation: {}, sum is: {}",
-                    self.generation,
-                    self.pots.iter().sum::<i64>()
-                );
-            }
+    fn find_cycle(&mut self) -> Option<Cycle> {
+        while self.cycle.is_none() && self.generation < self.max_generations {
             self.run_generation();
         }
-        self.pots.iter().sum()
+        self.cycle
     }
 
-    // For testing only.
-    // Returns a string representing the generation
+    // Returns the sum of the ids of the pots with plants after the given number of generations.
+    // Once a pattern repeats, any later generation is worked out from an earlier one in the
+    // cycle, shifted along once for each time the cycle has repeated since. Returns None if the
+    // given generation is past the maximum, and no pattern repeated by then.
 
-    fn generation_to_str(&self) -> String {
-        // if there are no pots, return an empty string
-        if let (Some(&left_most), Some(&right_most)) =
-            (self.pots.iter().min(), self.pots.iter().max())
-        {
-            (left_most..=right_most)
-                .map(|pot_id| {
-                    if self.pots.contains(&pot_id) {
-                        '#'
-                    } else {
-                        '.'
-                    }
-                })
-                .collect::<String>()
-        } else {
-            "".to_string()
+    fn sum_after(&mut self, generations: u64) -> Option<i64> {
+        while self.generation < generations.min(self.max_generations) && self.cycle.is_none() {
+            self.run_generation();
         }
+        if let Some(&(sum, _)) = self.history.get(generations as usize) {
+            return Some(sum);
+        }
+        let cycle = self.cycle?;
+        let repeats = (generations - cycle.first) / cycle.period;
+        let offset = (generations - cycle.first) % cycle.period;
+        let (sum, count) = self.history[(cycle.first + offset) as usize];
+        Some(sum + repeats as i64 * cycle.shift * count)
+    }
+
+    // Returns a string representing the generation, from the left-most to the right-most plant.
+
+    #[cfg(test)]
+    fn generation_to_str(&self) -> String {
+        self.row.to_string()
     }
 }
 
@@ -180,36 +182,17 @@ impl FromStr for Simulation {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut iter = s.lines();
-        let initial_state = match iter.next() {
-            None => return Err(Self::Err::from("invalid string")),
-            Some(s) => s,
-        };
-        let prefix = "initial state: ";
-        iter.next();
-
-        let pots = initial_state[prefix.len()..]
-            .as_bytes()
-            .iter()
-            .enumerate()
-            .filter(|(i, &c)| c == b'#')
-            .map(|(i, _)| i as PotId)
-            .collect::<HashSet<PotId>>();
-
-        let matches = iter
-            .filter_map(|line| {
-                if line.as_bytes()[9] == b'#' {
-                    Some(line[0..5].to_string())
-                } else {
-                    None
-                }
-            })
-            .collect::<HashSet<String>>();
-        Ok(Simulation {
-            pots,
-            matches,
-            generation: 0,
-        })
+        let mut iter = s.lines().enumerate().map(|(i, line)| (i + 1, line));
+        let initial_state = iter
+            .next()
+            .and_then(|(_, line)| line.strip_prefix("initial state: "))
+            .map(str::trim)
+            .filter(|state| state.bytes().all(|c| c == b'#' || c == b'.'))
+            .ok_or_else(|| Self::Err::from("invalid initial state"))?;
+
+        let row = Row::new(0, initial_state.bytes().map(|c| c == b'#'));
+        let rules = Rules::parse(iter)?;
+        Ok(Simulation::new(row, rules))
     }
 }
 
@@ -235,12 +218,12 @@ fn test_count_plants() -> Result<()> {
     ";
 
     let mut simulation = input.parse::<Simulation>()?;
-    assert_eq!(simulation.matches.len(), 14);
+    assert_eq!(simulation.rules.count(), 14);
     assert_eq!(simulation.generation_to_str(), "#..#.#..##......###...###");
     simulation.run_generation();
     assert_eq!(simulation.generation_to_str(), "#...#....#.....#..#..#..#");
 
-    assert_eq!(simulation.run(20), 325);
+    assert_eq!(simulation.sum_after(20), Some(325));
 
     println!("places counted pass!");
     Ok(())
@@ -252,3 +235,115 @@ fn test_str_slice() {
     assert_eq!("asdf"[1..3], "asdf"[1..3]);
     println!("slices equal!");
 }
+
+#[test]
+fn test_cycles() -> Result<()> {
+    // A single plant that moves one pot to the right every generation.
+    let input = "\
+        initial state: #\n\
+        \n\
+        .#... => #\
+    ";
+    let mut simulation = input.parse::<Simulation>()?;
+    assert_eq!(
+        simulation.find_cycle(),
+        Some(Cycle {
+            first: 0,
+            period: 1,
+            shift: 1
+        })
+    );
+    assert_eq!(simulation.sum_after(50_000_000_000), Some(50_000_000_000));
+
+    // A plant that alternates with two plants, moving 2 pots to the left every other generation.
+    let input = "\
+        initial state: #\n\
+        \n\
+        ..# => #\n\
+        .#. => #\
+    ";
+    let mut simulation = input.parse::<Simulation>()?;
+    simulation.run_generation();
+    assert_eq!(simulation.row.plants().collect::<Vec<_>>(), vec![-1, 0]);
+    assert_eq!(
+        simulation.find_cycle(),
+        Some(Cycle {
+            first: 0,
+            period: 2,
+            shift: -2
+        })
+    );
+    assert_eq!(simulation.sum_after(50_000_000_000), Some(-50_000_000_000));
+    assert_eq!(
+        simulation.sum_after(50_000_000_001),
+        Some(-1 - 2 * 50_000_000_000)
+    );
+    assert_eq!(simulation.sum_after(1), Some(-1));
+
+    // A row that grows by a plant on each side every generation never repeats.
+    let input = "\
+        initial state: ##\n\
+        \n\
+        ..# => #\n\
+        .#. => #\n\
+        .## => #\n\
+        #.. => #\n\
+        #.# => #\n\
+        ##. => #\n\
+        ### => #\
+    ";
+    let mut simulation = input.parse::<Simulation>()?;
+    simulation.max_generations = 100;
+    assert_eq!(simulation.find_cycle(), None);
+    assert_eq!(simulation.sum_after(100), Some(101));
+    assert_eq!(simulation.sum_after(101), None);
+
+    let input = std::fs::read_to_string("input/input.txt")?;
+    let mut simulation = input.parse::<Simulation>()?;
+    assert_eq!(simulation.sum_after(20), Some(2840));
+    assert_eq!(
+        simulation.sum_after(50_000_000_000),
+        Some(2_000_000_001_684)
+    );
+    // Extrapolating from the cycle is the same as simulating every generation.
+    for generations in [1_000, 1_001, 100_000] {
+        let mut direct = input.parse::<Simulation>()?;
+        while direct.generation < generations {
+            direct.run_generation();
+        }
+        assert_eq!(simulation.sum_after(generations), Some(direct.row.sum()));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_rule_widths() -> Result<()> {
+    // With 3 pot wide rules, each plant moves one pot to the right every generation.
+    let input = "\
+        initial state: #..#\n\
+        \n\
+        #.. => #\
+    ";
+    let mut simulation = input.parse::<Simulation>()?;
+    simulation.run_generation();
+    assert_eq!(simulation.row.plants().collect::<Vec<_>>(), vec![1, 4]);
+    assert_eq!(simulation.sum_after(10), Some(10 + 13));
+
+    // With 7 pot wide rules, plants 3 pots apart are replaced by two plants between them.
+    let input = "\
+        initial state: #..#\n\
+        \n\
+        #..#... => #\n\
+        .#..#.. => #\
+    ";
+    let mut simulation = input.parse::<Simulation>()?;
+    simulation.run_generation();
+    assert_eq!(simulation.generation_to_str(), "##");
+    assert_eq!(simulation.row.plants().collect::<Vec<_>>(), vec![2, 3]);
+
+    let input = "initial state: #\n\n..... => #";
+    assert!(input.parse::<Simulation>().is_err());
+    let input = "initial state: #x\n\n..#.. => #";
+    assert!(input.parse::<Simulation>().is_err());
+    Ok(())
+}
@@ -0,0 +1,168 @@
+use crate::rules::Rules;
+use crate::PotId;
+use std::fmt::{Display, Formatter};
+
+const WORD_BITS: usize = 64;
+
+// A row of pots, packed one bit per pot. Bit i is the pot with id start + i. The row is trimmed,
+// so unless it is empty, the first and last bits are both plants. Two rows that hold the same
+// pattern of plants are therefore equal apart from their start.
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Row {
+    words: Vec<u64>,
+    len: usize,
+    start: PotId,
+}
+
+impl Row {
+    pub fn new(start: PotId, plants: impl IntoIterator<Item = bool>) -> Self {
+        let mut builder = RowBuilder::new(start);
+        plants.into_iter().for_each(|plant| builder.push(plant));
+        builder.finish()
+    }
+
+    // The id of the left-most plant, if there are any.
+    pub fn start(&self) -> Option<PotId> {
+        if self.len == 0 {
+            None
+        } else {
+            Some(self.start)
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    pub fn sum(&self) -> i64 {
+        self.plants().sum()
+    }
+
+    // The packed pattern of plants, which is the same for two rows with the same pattern of
+    // plants, wherever they start, since rows are trimmed.
+    pub fn pattern(&self) -> &[u64] {
+        &self.words
+    }
+
+    // Returns the ids of the pots with plants, from left to right.
+    pub fn plants(&self) -> impl Iterator<Item = PotId> + '_ {
+        self.words.iter().enumerate().flat_map(move |(i, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(self.start + (i * WORD_BITS + bit) as PotId)
+            })
+        })
+    }
+
+    // Returns the next generation of the row. Every pot that could have a plant next generation,
+    // from the rules' reach before the first plant to their reach after the last, is worked out
+    // from a window of the pots around it, which is rolled along the row one bit at a time.
+
+    pub fn step(&self, rules: &Rules) -> Row {
+        let reach = rules.reach();
+        let window_mask = (1 << rules.width()) - 1;
+        let mut next = RowBuilder::new(self.start - reach as PotId);
+        let mut window = 0;
+        let mut remaining = self.len + 2 * reach;
+        // The padding words supply the empty pots after the end of the row.
+        let padding = std::iter::repeat_n(&0, (2 * reach).div_ceil(WORD_BITS) + 1);
+        for &word in self.words.iter().chain(padding) {
+            let bits = remaining.min(WORD_BITS);
+            for bit in 0..bits {
+                window = ((window << 1) | ((word >> bit) & 1) as usize) & window_mask;
+                next.push(rules.grows(window));
+            }
+            remaining -= bits;
+        }
+        next.finish()
+    }
+}
+
+impl Display for Row {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for i in 0..self.len {
+            let plant = (self.words[i / WORD_BITS] >> (i % WORD_BITS)) & 1 == 1;
+            write!(f, "{}", if plant { '#' } else { '.' })?;
+        }
+        Ok(())
+    }
+}
+
+// Builds a trimmed row one pot at a time, skipping empty pots before the first plant, and
+// dropping those after the last.
+
+struct RowBuilder {
+    row: Row,
+    pushed: usize,
+}
+
+impl RowBuilder {
+    fn new(start: PotId) -> Self {
+        RowBuilder {
+            row: Row {
+                start,
+                ..Row::default()
+            },
+            pushed: 0,
+        }
+    }
+
+    fn push(&mut self, plant: bool) {
+        if self.pushed == 0 && !plant {
+            self.row.start += 1;
+            return;
+        }
+        if self.pushed.is_multiple_of(WORD_BITS) {
+            self.row.words.push(0);
+        }
+        if plant {
+            self.row.words[self.pushed / WORD_BITS] |= 1 << (self.pushed % WORD_BITS);
+            self.row.len = self.pushed + 1;
+        }
+        self.pushed += 1;
+    }
+
+    fn finish(mut self) -> Row {
+        self.row.words.truncate(self.row.len.div_ceil(WORD_BITS));
+        if self.row.len == 0 {
+            self.row.start = 0;
+        }
+        self.row
+    }
+}
+
+#[test]
+fn test_row() {
+    let plants = "..#..#.#..##......###...###..".chars().map(|c| c == '#');
+    let row = Row::new(-2, plants);
+    assert_eq!(row.to_string(), "#..#.#..##......###...###");
+    assert_eq!(row.start(), Some(0));
+    assert_eq!(row.count(), 11);
+    assert_eq!(
+        row.pattern(),
+        Row::new(7, "#..#.#..##......###...###".chars().map(|c| c == '#')).pattern()
+    );
+    assert_eq!(row.sum(), 145);
+
+    // A row spanning several words.
+    let row = Row::new(10, (0..200).map(|i| i % 3 == 0));
+    assert_eq!(row.count(), 67);
+    assert_eq!(row.plants().last(), Some(208));
+    assert_eq!(
+        row.sum(),
+        (0..200).filter(|i| i % 3 == 0).map(|i| i + 10).sum()
+    );
+
+    let empty = Row::new(5, (0..100).map(|_| false));
+    assert_eq!(empty, Row::default());
+    assert_eq!(empty.start(), None);
+}
@@ -0,0 +1,273 @@
+use std::fmt::{Display, Formatter};
+
+// Wider neighbourhoods would need a rule table too big to be worth building.
+const MAX_WIDTH: usize = 15;
+
+#[derive(Debug, PartialEq)]
+pub enum RuleError {
+    // Line numbers start at 1, and count every line of the input.
+    InvalidRule {
+        line: usize,
+        text: String,
+    },
+    InvalidWidth {
+        line: usize,
+        width: usize,
+    },
+    WrongWidth {
+        line: usize,
+        width: usize,
+        expected: usize,
+    },
+    // The same pattern was given twice, first on the earlier line.
+    Duplicate {
+        pattern: String,
+        first: usize,
+        line: usize,
+    },
+    Conflict {
+        pattern: String,
+        first: usize,
+        line: usize,
+    },
+    // An empty neighbourhood growing a plant would fill the infinitely many empty pots.
+    UnboundedGrowth {
+        line: usize,
+    },
+    NoRules,
+}
+
+impl Display for RuleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleError::InvalidRule { line, text } => {
+                write!(f, "line {}: invalid rule {:?}", line, text)
+            }
+            RuleError::InvalidWidth { line, width } => write!(
+                f,
+                "line {}: a rule must cover an odd number of pots, at most {}, not {}",
+                line, MAX_WIDTH, width
+            ),
+            RuleError::WrongWidth {
+                line,
+                width,
+                expected,
+            } => write!(
+                f,
+                "line {}: rule covers {} pots, but the earlier rules cover {}",
+                line, width, expected
+            ),
+            RuleError::Duplicate {
+                pattern,
+                first,
+                line,
+            } => write!(
+                f,
+                "line {}: rule for {} repeats the rule on line {}",
+                line, pattern, first
+            ),
+            RuleError::Conflict {
+                pattern,
+                first,
+                line,
+            } => write!(
+                f,
+                "line {}: rule for {} contradicts the rule on line {}",
+                line, pattern, first
+            ),
+            RuleError::UnboundedGrowth { line } => write!(
+                f,
+                "line {}: empty pots growing a plant would make the row grow without bound",
+                line
+            ),
+            RuleError::NoRules => write!(f, "no rules given"),
+        }
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+// Whether a pot has a plant next generation, given the pattern of the pots around it. Patterns are
+// indexed as binary numbers, with the left-most pot as the highest bit and '#' as 1. Patterns
+// without a rule never grow a plant.
+
+#[derive(Debug)]
+pub struct Rules {
+    width: usize,
+    table: Vec<Option<bool>>,
+    // The line each pattern's rule was given on, to report duplicates.
+    lines: Vec<usize>,
+}
+
+impl Rules {
+    // The number of pots on either side of a pot that decide whether it has a plant.
+    pub fn reach(&self) -> usize {
+        self.width / 2
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn grows(&self, pattern: usize) -> bool {
+        self.table[pattern] == Some(true)
+    }
+
+    // The number of patterns that grow a plant.
+    pub fn count(&self) -> usize {
+        self.table
+            .iter()
+            .filter(|&&rule| rule == Some(true))
+            .count()
+    }
+
+    // The number of patterns that no rule was given for.
+    pub fn missing(&self) -> usize {
+        self.table.iter().filter(|rule| rule.is_none()).count()
+    }
+
+    // Parses rules like ".#.#. => #", given along with their line numbers. Blank lines are
+    // skipped. Every rule must cover the same odd number of pots.
+
+    pub fn parse<'a>(
+        lines: impl IntoIterator<Item = (usize, &'a str)>,
+    ) -> Result<Rules, RuleError> {
+        let mut rules: Option<Rules> = None;
+        for (line, text) in lines {
+            if text.trim().is_empty() {
+                continue;
+            }
+            let (pattern, plant) = parse_rule(text).ok_or_else(|| RuleError::InvalidRule {
+                line,
+                text: text.to_string(),
+            })?;
+            let width = pattern.len();
+            let rules = match &rules {
+                Some(rules) if rules.width != width => {
+                    return Err(RuleError::WrongWidth {
+                        line,
+                        width,
+                        expected: rules.width,
+                    })
+                }
+                Some(_) => rules.as_mut().unwrap(),
+                None if width % 2 == 0 || width > MAX_WIDTH => {
+                    return Err(RuleError::InvalidWidth { line, width })
+                }
+                None => rules.insert(Rules {
+                    width,
+                    table: vec![None; 1 << width],
+                    lines: vec![0; 1 << width],
+                }),
+            };
+
+            let index = pattern
+                .bytes()
+                .fold(0, |index, c| (index << 1) | (c == b'#') as usize);
+            if index == 0 && plant {
+                return Err(RuleError::UnboundedGrowth { line });
+            }
+            if let Some(previous) = rules.table[index] {
+                let pattern = pattern.to_string();
+                let first = rules.lines[index];
+                return Err(if previous == plant {
+                    RuleError::Duplicate {
+                        pattern,
+                        first,
+                        line,
+                    }
+                } else {
+                    RuleError::Conflict {
+                        pattern,
+                        first,
+                        line,
+                    }
+                });
+            }
+            rules.table[index] = Some(plant);
+            rules.lines[index] = line;
+        }
+        rules.ok_or(RuleError::NoRules)
+    }
+}
+
+// Splits a rule into its pattern and whether it grows a plant.
+
+fn parse_rule(text: &str) -> Option<(&str, bool)> {
+    let (pattern, result) = text.split_once("=>")?;
+    let pattern = pattern.trim();
+    if pattern.is_empty() || !pattern.bytes().all(|c| c == b'#' || c == b'.') {
+        return None;
+    }
+    match result.trim() {
+        "#" => Some((pattern, true)),
+        "." => Some((pattern, false)),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_parse_rules() -> Result<(), RuleError> {
+    let parse = |input: &str| Rules::parse(input.lines().enumerate().map(|(i, l)| (i + 1, l)));
+
+    let rules = parse("...## => #\n\n..#.. => #\n.#... => .\n")?;
+    assert_eq!(rules.width(), 5);
+    assert_eq!(rules.count(), 2);
+    assert_eq!(rules.missing(), 29);
+    assert!(rules.grows(0b00011));
+    assert!(!rules.grows(0b01000));
+
+    let rules = parse("#.. => #\n..# => .")?;
+    assert_eq!(rules.reach(), 1);
+    assert!(rules.grows(0b100));
+
+    assert_eq!(
+        parse("...## => #\n.##  #").unwrap_err(),
+        RuleError::InvalidRule {
+            line: 2,
+            text: String::from(".##  #")
+        }
+    );
+    assert_eq!(
+        parse("...## => x").unwrap_err(),
+        RuleError::InvalidRule {
+            line: 1,
+            text: String::from("...## => x")
+        }
+    );
+    assert_eq!(
+        parse("#..# => #").unwrap_err(),
+        RuleError::InvalidWidth { line: 1, width: 4 }
+    );
+    assert_eq!(
+        parse("...## => #\n..# => #").unwrap_err(),
+        RuleError::WrongWidth {
+            line: 2,
+            width: 3,
+            expected: 5
+        }
+    );
+    assert_eq!(
+        parse("...## => #\n#.#.# => .\n...## => #").unwrap_err(),
+        RuleError::Duplicate {
+            pattern: String::from("...##"),
+            first: 1,
+            line: 3
+        }
+    );
+    assert_eq!(
+        parse("...## => #\n...## => .").unwrap_err(),
+        RuleError::Conflict {
+            pattern: String::from("...##"),
+            first: 1,
+            line: 2
+        }
+    );
+    assert_eq!(
+        parse("...## => #\n..... => #").unwrap_err(),
+        RuleError::UnboundedGrowth { line: 2 }
+    );
+    assert!(parse("..... => .").is_ok());
+    assert_eq!(parse("\n").unwrap_err(), RuleError::NoRules);
+    Ok(())
+}